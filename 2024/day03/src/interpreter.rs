@@ -0,0 +1,141 @@
+use std::fmt::Display;
+
+use crate::Instruction;
+
+/// Runs instructions one at a time, optionally keeping a trace of what each
+/// one did.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    state: State,
+
+    conditionals: Conditionals,
+
+    // Only kept when asked for, as it holds an entry for every instruction.
+    trace: Option<Trace>,
+}
+
+/// Whether `do()` and `don't()` are obeyed.  Part 1 ignores them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conditionals {
+    Obey,
+    Ignore,
+}
+
+/// The machine state that instructions act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+    /// Whether `mul` instructions are applied.
+    pub enabled: bool,
+
+    /// The sum of all the applied `mul` instructions.  Each product fits in
+    /// 32 bits, but a long enough program could take the sum past that.
+    pub sum: u64,
+}
+
+/// What happened to a single instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub instruction: Instruction,
+
+    /// The byte offset of the instruction in the program text.
+    pub offset: usize,
+
+    /// Whether the instruction had any effect.
+    pub applied: bool,
+
+    /// The sum after the instruction.
+    pub sum: u64,
+}
+
+/// Every instruction run, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace(pub Vec<TraceEntry>);
+
+impl Interpreter {
+    pub fn new(conditionals: Conditionals) -> Self {
+        Self {
+            state: State {
+                enabled: true,
+                sum: 0,
+            },
+            conditionals,
+            trace: None,
+        }
+    }
+
+    /// Keeps a trace of every instruction run.
+    pub fn with_trace(self) -> Self {
+        Self {
+            trace: Some(Trace::default()),
+            ..self
+        }
+    }
+
+    /// The trace, if the interpreter was asked to keep one.
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// Runs every instruction, each given with its byte offset, and returns
+    /// the final state.
+    pub fn run(&mut self, program: impl IntoIterator<Item = (usize, Instruction)>) -> State {
+        for (offset, instruction) in program {
+            let applied = self.execute(&instruction);
+            if let Some(trace) = &mut self.trace {
+                trace.0.push(TraceEntry {
+                    instruction,
+                    offset,
+                    applied,
+                    sum: self.state.sum,
+                });
+            }
+        }
+        self.state
+    }
+
+    // Applies one instruction to the state, returning whether it did
+    // anything.
+    fn execute(&mut self, instruction: &Instruction) -> bool {
+        match *instruction {
+            Instruction::Mul(a, b) => {
+                if self.state.enabled {
+                    self.state.sum += u64::from(a) * u64::from(b);
+                }
+                self.state.enabled
+            }
+            Instruction::Do | Instruction::Dont if self.conditionals == Conditionals::Ignore => {
+                false
+            }
+            Instruction::Do => {
+                self.state.enabled = true;
+                true
+            }
+            Instruction::Dont => {
+                self.state.enabled = false;
+                true
+            }
+        }
+    }
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>6}  {:<12} {:<8} {}",
+            self.offset,
+            self.instruction.to_string(),
+            if self.applied { "applied" } else { "skipped" },
+            self.sum
+        )
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.0 {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,10 @@
+mod interpreter;
+
+use std::fmt::Display;
+
+use interpreter::{Conditionals, Interpreter};
+use scanner::{Keywords, Scanner};
+
 fn main() {
     part1();
     part2();
@@ -5,198 +12,185 @@ fn main() {
 
 fn part1() {
     let program = Program::new(include_str!("../data1.txt"));
-    let sum: u32 = program
-        .map(|inst| match inst {
-            Instruction::Mul(a, b) => a * b,
-            Instruction::Do => 0,
-            Instruction::Dont => 0,
-        })
-        .sum();
+    let mut interpreter = interpreter(Conditionals::Ignore);
+    let sum = interpreter.run(program).sum;
 
+    print_trace(&interpreter);
     println!("Part 1: {}", sum);
 }
+
 fn part2() {
     let program = Program::new(include_str!("../data2.txt"));
-    let mut enabled = true;
-    let sum: u32 = program
-        .map(|inst| match inst {
-            Instruction::Mul(a, b) => {
-                if enabled {
-                    a * b
-                } else {
-                    0
-                }
-            }
-            Instruction::Do => {
-                enabled = true;
-                0
-            }
-            Instruction::Dont => {
-                enabled = false;
-                0
-            }
-        })
-        .sum();
+    let mut interpreter = interpreter(Conditionals::Obey);
+    let sum = interpreter.run(program).sum;
 
+    print_trace(&interpreter);
     println!("Part 2: {}", sum);
 }
 
-#[derive(Debug)]
+// An interpreter that keeps a trace of what every instruction did if it's
+// going to be shown (--trace).
+fn interpreter(conditionals: Conditionals) -> Interpreter {
+    let interpreter = Interpreter::new(conditionals);
+    if std::env::args().any(|arg| arg == "--trace") {
+        interpreter.with_trace()
+    } else {
+        interpreter
+    }
+}
+
+fn print_trace(interpreter: &Interpreter) {
+    if let Some(trace) = interpreter.trace() {
+        print!("{}", trace);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Instruction {
     Mul(u32, u32),
     Do,
     Dont,
 }
 
-struct Program {
-    code: &'static str,
-    cursor: usize,
-}
-
-impl Program {
-    fn new(code: &'static str) -> Self {
-        Self { code, cursor: 0 }
-    }
-
-    // Find the first instance of a string from a set of strings in the code.
-    // It returns (text-index, cursor)
-    fn start_parse_strings(&self, cursor: usize, texts: &[&'static str]) -> Option<(usize, usize)> {
-        let cursors = texts
-            .iter()
-            .enumerate()
-            // Convert to (text, optional cursor) tuple
-            .map(|(i, text)| (i, self.start_parse_string(cursor, *text)));
-
-        // Reduce to the minimum cursor
-        let cursor = cursors.reduce(|(a_text, a_cursor), (b_text, b_cursor)| {
-            if a_cursor.is_none() {
-                return (b_text, b_cursor);
-            }
-            if b_cursor.is_none() {
-                return (a_text, a_cursor);
-            }
-            if a_cursor.unwrap() < b_cursor.unwrap() {
-                (a_text, a_cursor)
-            } else if a_cursor.unwrap() == b_cursor.unwrap() {
-                if texts[a_text].len() < texts[b_text].len() {
-                    (b_text, b_cursor)
-                } else {
-                    (a_text, a_cursor)
-                }
-            } else {
-                (b_text, b_cursor)
-            }
-        });
-
-        if let Some((text, cursor)) = cursor {
-            if let Some(cursor) = cursor {
-                Some((text, cursor))
-            } else {
-                None
-            }
-        } else {
-            None
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Mul(a, b) => write!(f, "mul({},{})", a, b),
+            Instruction::Do => write!(f, "do()"),
+            Instruction::Dont => write!(f, "don't()"),
         }
     }
+}
 
-    // Looks for an initial string and updates the cursor to the end of the string.
-    fn start_parse_string(&self, cursor: usize, text: &'static str) -> Option<usize> {
-        let cursor = self.code[cursor..]
-            .find(text)
-            .map(|i| i + text.len() + cursor);
-        cursor
-    }
+struct Program<'a> {
+    scanner: Scanner<'a>,
+    keywords: Keywords<'static>,
+}
 
-    // Read a string at the actual cursor position.
-    fn parse_string(&self, cursor: usize, text: &'static str) -> Option<usize> {
-        let end = cursor + text.len();
-        let end = usize::min(end, self.code.len());
+// The start of every instruction.  Their order gives the index that
+// `find_next` returns.
+const KEYWORDS: [&str; 3] = ["mul(", "don't()", "do()"];
 
-        // Check if the string is at the cursor position
-        if self.code[cursor..end].starts_with(text) {
-            Some(end)
-        } else {
-            None
+impl<'a> Program<'a> {
+    fn new(code: &'a str) -> Self {
+        Self {
+            scanner: Scanner::new(code),
+            keywords: Keywords::new(&KEYWORDS),
         }
     }
 
-    fn parse_number(&self, cursor: usize, max_num: u32) -> Option<(usize, u32)> {
-        // Create an iterator that will cover the next characters until a non-digit character is found.
-        let iter = self.code[cursor..].chars().take_while(|c| c.is_digit(10));
-        let num_digits = iter.count();
-
-        if num_digits == 0 {
-            return None;
-        }
+    // Reads the rest of a `mul(` instruction: a number (maximum 3 digits), a
+    // comma, another number and a closing parenthesis.  On failure the search
+    // for the next instruction carries on from wherever this stopped.
+    fn parse_mul(&mut self) -> Option<Instruction> {
+        let a = self.scanner.number_bounded(999)?;
+        self.scanner.expect_literal(",")?;
+        let b = self.scanner.number_bounded(999)?;
+        self.scanner.expect_literal(")")?;
+        Some(Instruction::Mul(a, b))
+    }
+}
 
-        // Convert the digits to a number.
-        let num = self.code[cursor..cursor + num_digits].parse::<u32>().ok()?;
+// Yields each instruction with its byte offset in the code.
+impl Iterator for Program<'_> {
+    type Item = (usize, Instruction);
 
-        if num > max_num {
-            return None;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keyword, span) = self.scanner.find_next(&self.keywords)?;
+            let instruction = match keyword {
+                0 => match self.parse_mul() {
+                    Some(instruction) => instruction,
+                    None => continue,
+                },
+                1 => Instruction::Dont,
+                2 => Instruction::Do,
+                _ => unreachable!("Unknown keyword"),
+            };
+            return Some((span.start, instruction));
         }
-
-        Some((cursor + num_digits, num))
     }
 }
 
-impl Iterator for Program {
-    type Item = Instruction;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let keywords = ["mul(", "don't()", "do()"];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::{State, Trace, TraceEntry};
+
+    #[test]
+    fn test_program() {
+        let program = Program::new(include_str!("../test_data2.txt"));
+        assert_eq!(
+            program
+                .map(|(_, instruction)| instruction)
+                .collect::<Vec<_>>(),
+            vec![
+                Instruction::Mul(2, 4),
+                Instruction::Dont,
+                Instruction::Mul(5, 5),
+                Instruction::Mul(11, 8),
+                Instruction::Do,
+                Instruction::Mul(8, 5),
+            ]
+        );
+
+        // A broken instruction doesn't hide the one after it.
+        let program = Program::new("mul(1,mul(2,3)mul(4,1000)mul(5,6)");
+        assert_eq!(
+            program.collect::<Vec<_>>(),
+            vec![(6, Instruction::Mul(2, 3)), (25, Instruction::Mul(5, 6))]
+        );
+    }
 
-        // Find the next sequence of `mul(` followed by a number (maximum 3 digits) and a comma
-        // followed by another number (maximum 3 digits) and a closing parenthesis.
-        // Return the slice of the code that matches this pattern.
-        loop {
-            if let Some((text, cursor)) = self.start_parse_strings(self.cursor, &keywords[..]) {
-                self.cursor = cursor;
-                match text {
-                    0 => {
-                        let num_a = self.parse_number(self.cursor, 999);
-                        if num_a.is_none() {
-                            continue;
-                        }
-                        let (cursor, a) = num_a.unwrap();
-                        self.cursor = cursor;
-
-                        if let Some(cursor) = self.parse_string(self.cursor, ",") {
-                            self.cursor = cursor;
-                        } else {
-                            continue;
-                        }
-
-                        let num_b = self.parse_number(self.cursor, 999);
-                        if num_b.is_none() {
-                            continue;
-                        }
-                        let (cursor, b) = num_b.unwrap();
-                        self.cursor = cursor;
-
-                        if let Some(cursor) = self.parse_string(self.cursor, ")") {
-                            self.cursor = cursor;
-                        } else {
-                            continue;
-                        }
-
-                        return Some(Instruction::Mul(a, b));
-                    }
-                    1 => {
-                        return Some(Instruction::Dont);
-                    }
-
-                    2 => {
-                        return Some(Instruction::Do);
-                    }
-
-                    _ => {
-                        return None;
-                    }
-                }
-            } else {
-                return None;
+    #[test]
+    fn test_interpreter() {
+        let program = || Program::new(include_str!("../test_data2.txt"));
+        let entry = |instruction, offset, applied, sum| TraceEntry {
+            instruction,
+            offset,
+            applied,
+            sum,
+        };
+
+        let mut interpreter = Interpreter::new(Conditionals::Obey).with_trace();
+        assert_eq!(
+            interpreter.run(program()),
+            State {
+                enabled: true,
+                sum: 48
             }
-        }
+        );
+        assert_eq!(
+            interpreter.trace(),
+            Some(&Trace(vec![
+                entry(Instruction::Mul(2, 4), 1, true, 8),
+                entry(Instruction::Dont, 20, true, 8),
+                entry(Instruction::Mul(5, 5), 28, false, 8),
+                entry(Instruction::Mul(11, 8), 48, false, 8),
+                entry(Instruction::Do, 59, true, 8),
+                entry(Instruction::Mul(8, 5), 64, true, 48),
+            ]))
+        );
+
+        // Without conditionals, do() and don't() do nothing.
+        let mut interpreter = Interpreter::new(Conditionals::Ignore).with_trace();
+        assert_eq!(interpreter.run(program()).sum, 161);
+        assert_eq!(
+            interpreter.trace().unwrap().to_string().lines().nth(1),
+            Some("    20  don't()      skipped  8")
+        );
+
+        // No trace is kept unless asked for.
+        let mut interpreter = Interpreter::new(Conditionals::Ignore);
+        assert_eq!(interpreter.run(program()).sum, 161);
+        assert_eq!(interpreter.trace(), None);
+    }
+
+    #[test]
+    fn test_large_sum() {
+        // Enough products to take the sum past u32::MAX.
+        let code = "mul(999,999)".repeat(5000);
+        let mut interpreter = Interpreter::new(Conditionals::Obey);
+        assert_eq!(interpreter.run(Program::new(&code)).sum, 4_990_005_000);
     }
 }
@@ -0,0 +1,22 @@
+#![no_main]
+
+// Run with `cargo +nightly fuzz run parse` from the day04 directory.
+
+use libfuzzer_sys::fuzz_target;
+
+// The day is a binary, so pull in its parser directly.
+#[path = "../../src/parse.rs"]
+mod parse;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    // Any text either parses into a rectangular grid or is rejected, but
+    // never panics.
+    if let Ok(letters) = parse::parse(text) {
+        assert!(letters.width > 0 && letters.height > 0);
+        assert_eq!(letters.grid.len(), letters.width * letters.height);
+    }
+});
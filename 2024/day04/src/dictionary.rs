@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use grid_edges::Edges;
+use meaningful_lines::MeaningfulLines;
+
+use crate::{Direction, FoundWord, Wordsearch};
+
+/// A list of words to find in a wordsearch all at once, using an
+/// Aho-Corasick automaton so that every line of the grid only needs reading
+/// once in each direction, however many words there are.
+#[derive(Debug)]
+pub struct Dictionary {
+    words: Vec<String>,
+    nodes: Vec<Node>,
+}
+
+/// Which matches to report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// Every match, so a palindrome is found once in each direction, and a
+    /// word inside a longer one (e.g. MAS in XMAS) is found as well.
+    All,
+
+    /// Each set of cells once: a palindrome is only reported in one direction,
+    /// and words lying within a longer match (e.g. MAS in XMAS) are dropped.
+    Once,
+}
+
+// A state of the automaton: the prefix of one or more words read so far.
+#[derive(Debug, Default)]
+struct Node {
+    next: HashMap<char, usize>,
+
+    // The state for the longest proper suffix of this prefix that is also a
+    // prefix of some word.
+    fail: usize,
+
+    // The words that end here, including those ending at a suffix.
+    words: Vec<usize>,
+}
+
+impl Dictionary {
+    pub fn new<S: AsRef<str>>(words: impl IntoIterator<Item = S>) -> Self {
+        let words = words
+            .into_iter()
+            .map(|word| word.as_ref().to_string())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        // Build a trie of the words...
+        let mut nodes = vec![Node::default()];
+        for (index, word) in words.iter().enumerate() {
+            let mut node = 0;
+            for c in word.chars() {
+                node = match nodes[node].next.get(&c) {
+                    Some(&next) => next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[node].next.insert(c, next);
+                        next
+                    }
+                };
+            }
+            nodes[node].words.push(index);
+        }
+
+        // ...then add the failure links, breadth first so that a node's
+        // failure link is always done before those of its children.
+        let mut queue = nodes[0].next.values().copied().collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            let children = nodes[node]
+                .next
+                .iter()
+                .map(|(&c, &child)| (c, child))
+                .collect::<Vec<_>>();
+            for (c, child) in children {
+                let mut fail = nodes[node].fail;
+                while fail != 0 && !nodes[fail].next.contains_key(&c) {
+                    fail = nodes[fail].fail;
+                }
+                let fail = nodes[fail].next.get(&c).copied().unwrap_or(0);
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].words.clone();
+                nodes[child].words.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        Self { words, nodes }
+    }
+
+    /// Reads a word list with one word per line.
+    pub fn from_file(path: &str) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::new(MeaningfulLines::new(&text).map(str::trim)))
+    }
+
+    // The number of letters in the longest word.
+    fn longest(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Feeds a line of characters through the automaton, returning each word
+    // found along with the index of its last character.
+    fn scan(&self, line: impl Iterator<Item = char>) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut node = 0;
+        for (i, c) in line.enumerate() {
+            while node != 0 && !self.nodes[node].next.contains_key(&c) {
+                node = self.nodes[node].fail;
+            }
+            node = self.nodes[node].next.get(&c).copied().unwrap_or(0);
+            found.extend(self.nodes[node].words.iter().map(|&word| (word, i)));
+        }
+        found
+    }
+}
+
+// A row, column or diagonal of the grid.
+struct GridLine {
+    cells: Vec<(usize, usize)>,
+
+    // The directions of reading the cells in order and in reverse.
+    forwards: Direction,
+    backwards: Direction,
+
+    // On a wrapped grid, the line comes back round to its first cell.
+    cyclic: bool,
+}
+
+impl Wordsearch {
+    // Every row, column and diagonal of the grid.
+    fn lines(&self) -> Vec<GridLine> {
+        let size = self.size();
+        let mut lines = Vec::new();
+        let families = [
+            (Direction::Right, Direction::Left),
+            (Direction::Down, Direction::Up),
+            (Direction::DownRight, Direction::UpLeft),
+            (Direction::DownLeft, Direction::UpRight),
+        ];
+
+        for (forwards, backwards) in families {
+            let delta = forwards.delta();
+            let mut covered = vec![false; self.width * self.height];
+
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    // On a bounded grid a line starts at a cell whose previous
+                    // cell is off the grid.  On a wrapped grid every line is a
+                    // loop, and starts from whichever of its cells comes first.
+                    let previous = self.edges.offset((x, y), delta, -1, size);
+                    if covered[y * self.width + x]
+                        || (self.edges == Edges::Bounded && previous.is_some())
+                    {
+                        continue;
+                    }
+
+                    let mut cells = Vec::new();
+                    let mut cell = Some((x, y));
+                    while let Some((x, y)) = cell {
+                        if covered[y * self.width + x] {
+                            break;
+                        }
+                        covered[y * self.width + x] = true;
+                        cells.push((x, y));
+                        cell = self.edges.step((x, y), delta, size);
+                    }
+                    lines.push(GridLine {
+                        cells,
+                        forwards,
+                        backwards,
+                        cyclic: self.edges == Edges::Wrap,
+                    });
+                }
+            }
+        }
+        lines
+    }
+
+    /// Finds every word of the dictionary, in any of the 8 directions.
+    pub fn find_dictionary_words(&self, dictionary: &Dictionary, report: Report) -> Vec<FoundWord> {
+        let mut found = Vec::new();
+        for line in self.lines() {
+            let length = line.cells.len();
+
+            // Going round a loop, read on past the start far enough to find
+            // the words that cross it.
+            let overrun = if line.cyclic {
+                dictionary.longest().saturating_sub(1)
+            } else {
+                0
+            };
+
+            let forwards = (0..length).collect::<Vec<_>>();
+            let backwards = (0..length).rev().collect::<Vec<_>>();
+            for (order, direction) in [(forwards, line.forwards), (backwards, line.backwards)] {
+                let cell = |i: usize| line.cells[order[i % length]];
+                let letters = (0..length + overrun).map(|i| {
+                    let (x, y) = cell(i);
+                    self.grid[y * self.width + x]
+                });
+
+                for (word, end) in dictionary.scan(letters) {
+                    let word = &dictionary.words[word];
+                    let start = end + 1 - word.chars().count();
+
+                    // Anything starting after going all the way round has
+                    // been found already.
+                    if start >= length {
+                        continue;
+                    }
+
+                    found.push(FoundWord {
+                        word: word.clone(),
+                        start: cell(start),
+                        end: cell(end),
+                        direction,
+                        cells: (start..=end).map(cell).collect(),
+                    });
+                }
+            }
+        }
+
+        if report == Report::Once {
+            // Longest first, so that a match can be dropped if its cells are
+            // all part of one that's already been kept.
+            found.sort_by_key(|found| std::cmp::Reverse(found.word.chars().count()));
+            let mut kept: Vec<HashSet<(usize, usize)>> = Vec::new();
+            found.retain(|found| {
+                let cells = found.cells().into_iter().collect::<HashSet<_>>();
+                if kept.iter().any(|kept| cells.is_subset(kept)) {
+                    return false;
+                }
+                kept.push(cells);
+                true
+            });
+        }
+
+        found
+    }
+}
@@ -1,35 +1,189 @@
 #![allow(unused)]
 
-use std::fmt::Display;
+mod dictionary;
+mod parse;
+mod render;
+mod template;
 
+use std::{
+    fmt::Display,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use dictionary::{Dictionary, Report};
+use grid_edges::Edges;
+use grid_image::{Canvas, Rgb};
 use meaningful_lines::MeaningfulLines;
+use parse::{Letters, ParseError};
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, EnumString};
+use template::{Orientations, Template, TemplateMatch};
+
+// The X-MAS shape from part 2: two diagonal MASes crossing at the A.
+const X_MAS: &str = "
+M.S
+.A.
+M.S
+";
 
 fn main() {
+    // Search for a template from a file (--template FILE), in all
+    // orientations with --all-orientations.
+    if let Some(path) = arg_value("--template") {
+        let pattern = std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("Unable to read {}: {}", path, error));
+        let orientations = if std::env::args().any(|arg| arg == "--all-orientations") {
+            Orientations::All
+        } else {
+            Orientations::Exact
+        };
+
+        let grid = load_grid();
+        let matches = grid.find_template(&Template::new(&pattern), orientations);
+        for found in &matches {
+            println!("{},{}:\n{}", found.anchor.0, found.anchor.1, found.template);
+        }
+        println!("Number of times found: {}", matches.len());
+        return;
+    }
+
+    // Find all the words in a word list (--dictionary FILE), reporting each
+    // set of cells only once with --once.
+    if let Some(path) = arg_value("--dictionary") {
+        let dictionary = Dictionary::from_file(&path)
+            .unwrap_or_else(|error| panic!("Unable to read {}: {}", path, error));
+        let report = if std::env::args().any(|arg| arg == "--once") {
+            Report::Once
+        } else {
+            Report::All
+        };
+
+        let grid = load_grid();
+        let direction = direction_filter();
+        let found_words = grid
+            .find_dictionary_words(&dictionary, report)
+            .into_iter()
+            .filter(|word| direction.is_none_or(|direction| word.direction == direction))
+            .collect::<Vec<_>>();
+        if show_requested() {
+            show(&grid, found_words.iter().flat_map(FoundWord::cells));
+        }
+        for word in &found_words {
+            println!(
+                "{} from {},{} to {},{} ({:?})",
+                word.word, word.start.0, word.start.1, word.end.0, word.end.1, word.direction
+            );
+        }
+        println!("Number of words found: {}", found_words.len());
+        return;
+    }
+
     part1();
     part2();
 }
 
 fn part1() {
-    let grid = Wordsearch::new(include_str!("../data.txt"));
-    let found_words = grid.find_words("XMAS");
+    // A word can be written in 8 directions: the 4 rotations of a line and
+    // of a diagonal.
+    let grid = load_grid();
+    let found_words = [Template::line("XMAS"), Template::diagonal("XMAS")]
+        .iter()
+        .flat_map(|template| grid.find_template(template, Orientations::All))
+        .collect::<Vec<_>>();
     println!("Number of times found: {}", found_words.len());
+
+    // Templates don't know which way a word reads, so show the words found
+    // directly, which do.
+    if show_requested() {
+        let direction = direction_filter();
+        let words = grid
+            .find_words("XMAS")
+            .into_iter()
+            .filter(|word| direction.is_none_or(|direction| word.direction == direction))
+            .collect::<Vec<_>>();
+        show(&grid, words.iter().flat_map(FoundWord::cells));
+    }
+
+    if let Some(directory) = image_directory() {
+        let cells = found_words.iter().flat_map(TemplateMatch::cells);
+        grid.save_image(directory.join("part1.png"), cells)
+            .expect("Unable to write image");
+    }
 }
 
 fn part2() {
-    let grid = Wordsearch::new(include_str!("../data.txt"));
-    let found_words = grid.find_x_words("MAS");
+    let grid = load_grid();
+    let found_words = grid.find_template(&Template::new(X_MAS), Orientations::All);
     println!("Number of times found: {}", found_words.len());
+    if show_requested() {
+        show(&grid, found_words.iter().flat_map(TemplateMatch::cells));
+    }
+
+    if let Some(directory) = image_directory() {
+        let cells = found_words.iter().flat_map(TemplateMatch::cells);
+        grid.save_image(directory.join("part2.png"), cells)
+            .expect("Unable to write image");
+    }
+}
+
+// Loads the puzzle input, with words wrapping round the edges with --wrap.
+fn load_grid() -> Wordsearch {
+    let edges = if std::env::args().any(|arg| arg == "--wrap") {
+        Edges::Wrap
+    } else {
+        Edges::Bounded
+    };
+    Wordsearch::new(include_str!("../data.txt")).with_edges(edges)
+}
+
+// Returns the value following a command line flag, e.g. `--template x.txt`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    Some(
+        args.next()
+            .unwrap_or_else(|| panic!("{} needs a value", name)),
+    )
+}
+
+// Whether to print the grid with only the found words showing (--show).
+fn show_requested() -> bool {
+    std::env::args().any(|arg| arg == "--show")
+}
+
+// Prints the grid with only the given cells showing, highlighted in colour
+// on a terminal.
+fn show(grid: &Wordsearch, cells: impl IntoIterator<Item = (usize, usize)>) {
+    let colour = std::io::stdout().is_terminal();
+    println!("{}", grid.highlight(cells, colour));
+}
+
+// Only show words read in this direction (--direction up-left etc.).
+fn direction_filter() -> Option<Direction> {
+    arg_value("--direction").map(|direction| {
+        direction
+            .parse()
+            .unwrap_or_else(|_| panic!("Unknown direction: {}", direction))
+    })
+}
+
+// The directory to write images of the found words to (--image DIR).
+fn image_directory() -> Option<PathBuf> {
+    arg_value("--image").map(PathBuf::from)
 }
 
 struct Wordsearch {
     grid: Vec<char>,
     width: usize,
     height: usize,
+
+    // Whether words can run off one edge and continue from the opposite one.
+    edges: Edges,
 }
 
-#[derive(Debug, Clone, Copy, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, EnumString)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
 enum Direction {
     Up,
     Down,
@@ -47,166 +201,116 @@ struct FoundWord {
     start: (usize, usize),
     end: (usize, usize),
     direction: Direction,
+
+    // Every cell the word covers, from start to end.
+    cells: Vec<(usize, usize)>,
 }
 
 #[derive(Debug)]
 struct FoundXWord {
     word: String,
     start: (usize, usize),
+
+    // Every cell covered by the two diagonals crossing at `start`.
+    cells: Vec<(usize, usize)>,
 }
 
-impl Wordsearch {
-    fn new(data: &'static str) -> Self {
-        let height = MeaningfulLines::new(data).count();
-        let width = MeaningfulLines::new(data)
-            .next()
-            .expect("Need at least one line in the data, and all lines should be the same length")
-            .len();
-
-        let mut grid = Vec::with_capacity(width * height);
-
-        // Let's fill the grid with the data
-        for line in MeaningfulLines::new(data) {
-            for c in line.chars() {
-                grid.push(c);
-            }
-        }
-        if grid.len() != width * height {
-            panic!("Something went wrong while filling the grid");
+impl Direction {
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::UpRight => (1, -1),
+            Direction::DownLeft => (-1, 1),
+            Direction::DownRight => (1, 1),
         }
+    }
+}
 
-        Wordsearch {
+impl FoundWord {
+    fn cells(&self) -> Vec<(usize, usize)> {
+        self.cells.clone()
+    }
+}
+
+impl FoundXWord {
+    fn cells(&self) -> Vec<(usize, usize)> {
+        self.cells.clone()
+    }
+}
+
+impl Wordsearch {
+    fn new(data: &str) -> Self {
+        Self::parse(data).unwrap_or_else(|error| panic!("Invalid wordsearch: {}", error))
+    }
+
+    fn parse(data: &str) -> Result<Self, ParseError> {
+        let Letters {
             grid,
             width,
             height,
-        }
+        } = parse::parse(data)?;
+
+        Ok(Wordsearch {
+            grid,
+            width,
+            height,
+            edges: Edges::Bounded,
+        })
+    }
+
+    fn with_edges(self, edges: Edges) -> Self {
+        Self { edges, ..self }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    // The cells along a line of the given length from `start`, or `None` if
+    // it runs off a bounded grid.
+    fn line_cells(
+        &self,
+        start: (usize, usize),
+        direction: Direction,
+        length: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        (0..length as isize)
+            .map(|i| self.edges.offset(start, direction.delta(), i, self.size()))
+            .collect()
     }
 
+    // Checks for the word reading from `start` in the given direction, and
+    // returns the position of its last letter.  The word must fit entirely
+    // within the grid.
     fn find_word_in_direction(
         &self,
         word: &str,
         start: (usize, usize),
         direction: Direction,
     ) -> Option<(usize, usize)> {
-        let (mut x, mut y) = start;
-
-        // Check to see if the possible word can actually fit in the grid based
-        // on the direction.
-        match direction {
-            Direction::Up => {
-                if y < word.len() - 1 {
-                    return None;
-                }
-            }
-            Direction::Down => {
-                if y + word.len() > self.height {
-                    return None;
-                }
-            }
-            Direction::Left => {
-                if x < word.len() - 1 {
-                    return None;
-                }
-            }
-            Direction::Right => {
-                if x + word.len() > self.width {
-                    return None;
-                }
-            }
-            Direction::UpLeft => {
-                if x < word.len() - 1 || y < word.len() - 1 {
-                    return None;
-                }
-            }
-            Direction::UpRight => {
-                if x + word.len() > self.width || y < word.len() - 1 {
-                    return None;
-                }
-            }
-            Direction::DownLeft => {
-                if x < word.len() - 1 || y + word.len() > self.height {
-                    return None;
-                }
-            }
-            Direction::DownRight => {
-                if x + word.len() > self.width || y + word.len() > self.height {
-                    return None;
-                }
-            }
+        let length = word.chars().count() as isize;
+        if length == 0 {
+            return None;
         }
 
-        let mut word_chars = word.chars();
-        let mut current_char = word_chars.next().unwrap();
-        loop {
-            if self.grid[y * self.width + x] == current_char {
-                // Check to see if we've reached the end of the word.
-                // If so, return it's final position.
-                let next_char = word_chars.next();
-                if next_char.is_none() {
-                    return Some((x, y));
-                }
-
-                // Otherwise, continue checking the rest of the word.
-                match direction {
-                    Direction::Up => {
-                        if y == 0 {
-                            return None;
-                        }
-                        y -= 1;
-                    }
-                    Direction::Down => {
-                        if y == self.height - 1 {
-                            return None;
-                        }
-                        y += 1;
-                    }
-                    Direction::Left => {
-                        if x == 0 {
-                            return None;
-                        }
-                        x -= 1;
-                    }
-                    Direction::Right => {
-                        if x == self.width - 1 {
-                            return None;
-                        }
-                        x += 1;
-                    }
-                    Direction::UpLeft => {
-                        if x == 0 || y == 0 {
-                            return None;
-                        }
-                        x -= 1;
-                        y -= 1;
-                    }
-                    Direction::UpRight => {
-                        if x == self.width - 1 || y == 0 {
-                            return None;
-                        }
-                        x += 1;
-                        y -= 1;
-                    }
-                    Direction::DownLeft => {
-                        if x == 0 || y == self.height - 1 {
-                            return None;
-                        }
-                        x -= 1;
-                        y += 1;
-                    }
-                    Direction::DownRight => {
-                        if x == self.width - 1 || y == self.height - 1 {
-                            return None;
-                        }
-                        x += 1;
-                        y += 1;
-                    }
-                }
-
-                current_char = next_char.unwrap();
-            } else {
-                return None;
-            }
-        }
+        // On a bounded grid, if both ends are on it, every letter in between
+        // is too.  A wrapped grid has no ends.
+        let delta = direction.delta();
+        let end = self.edges.offset(start, delta, length - 1, self.size())?;
+        word.chars()
+            .enumerate()
+            .all(|(i, c)| {
+                let (x, y) = self
+                    .edges
+                    .offset(start, delta, i as isize, self.size())
+                    .unwrap();
+                self.grid[y * self.width + x] == c
+            })
+            .then_some(end)
     }
 
     fn find_words(&self, word: &str) -> Vec<FoundWord> {
@@ -223,6 +327,9 @@ impl Wordsearch {
                             start: (x, y),
                             end: (end_x, end_y),
                             direction,
+                            cells: self
+                                .line_cells((x, y), direction, word.chars().count())
+                                .unwrap(),
                         };
                         words.push(found_word);
                     }
@@ -233,64 +340,67 @@ impl Wordsearch {
     }
 
     fn find_x_word_at(&self, x: usize, y: usize, word: &str) -> Option<FoundXWord> {
-        assert_eq!(word.len() % 2, 1, "Word length must be odd");
+        let length = word.chars().count();
+        assert_eq!(length % 2, 1, "Word length must be odd");
 
-        let reach = (word.len() - 1) / 2;
-        if reach > x || reach > y || reach > self.width - x || reach > self.height - y {
+        // Both diagonals must fit within a bounded grid.
+        let reach = (length - 1) / 2;
+        let bounded = self.edges == Edges::Bounded;
+        if bounded
+            && (reach > x || reach > y || x + reach >= self.width || y + reach >= self.height)
+        {
             return None;
         }
 
+        // The cell `reach` steps away from the centre in the given direction.
+        let corner = |direction: Direction| {
+            self.edges
+                .offset((x, y), direction.delta(), reach as isize, self.size())
+                .unwrap()
+        };
+
         // Check the top-left to bottom-right diagonal
+        let down_right = corner(Direction::UpLeft);
+        if self
+            .find_word_in_direction(word, down_right, Direction::DownRight)
+            .is_none()
         {
-            let x = x - reach;
-            let y = y - reach;
-            if self
-                .find_word_in_direction(word, (x, y), Direction::DownRight)
-                .is_none()
-            {
-                // Check the bottom-right to top-left diagonal
-                let x = x + 2 * reach;
-                let y = y + 2 * reach;
-                if self
-                    .find_word_in_direction(word, (x, y), Direction::UpLeft)
-                    .is_none()
-                {
-                    return None;
-                }
-            }
+            // Check the bottom-right to top-left diagonal
+            let up_left = corner(Direction::DownRight);
+            self.find_word_in_direction(word, up_left, Direction::UpLeft)?;
         }
+
         // Check the bottom-left to top-right diagonal
+        let up_right = corner(Direction::DownLeft);
+        if self
+            .find_word_in_direction(word, up_right, Direction::UpRight)
+            .is_none()
         {
-            let x = x - reach;
-            let y = y + reach;
-            if self
-                .find_word_in_direction(word, (x, y), Direction::UpRight)
-                .is_none()
-            {
-                // Check the top-right to bottom-left diagonal
-                let x = x + 2 * reach;
-                let y = y - 2 * reach;
-                if self
-                    .find_word_in_direction(word, (x, y), Direction::DownLeft)
-                    .is_none()
-                {
-                    return None;
-                }
-            }
+            // Check the top-right to bottom-left diagonal
+            let down_left = corner(Direction::UpRight);
+            self.find_word_in_direction(word, down_left, Direction::DownLeft)?;
         }
 
+        let mut cells = self.line_cells(down_right, Direction::DownRight, length)?;
+        cells.extend(self.line_cells(up_right, Direction::UpRight, length)?);
         Some(FoundXWord {
             word: word.to_string(),
             start: (x, y),
+            cells,
         })
     }
 
     fn find_x_words(&self, word: &str) -> Vec<FoundXWord> {
         let mut words = Vec::new();
-        let reach = (word.len() - 1) / 2;
 
-        for y in reach..self.height - reach {
-            for x in reach..self.width - reach {
+        // On a bounded grid, the X can't be centred too close to the edges.
+        let reach = match self.edges {
+            Edges::Bounded => word.chars().count().saturating_sub(1) / 2,
+            Edges::Wrap => 0,
+        };
+
+        for y in reach..self.height.saturating_sub(reach) {
+            for x in reach..self.width.saturating_sub(reach) {
                 if let Some(found_word) = self.find_x_word_at(x, y, word) {
                     words.push(found_word);
                 }
@@ -300,6 +410,24 @@ impl Wordsearch {
     }
 }
 
+impl Wordsearch {
+    // Draws the grid with each letter in its own shade, and the given cells
+    // (usually those of found words) highlighted.
+    fn save_image(
+        &self,
+        path: impl AsRef<Path>,
+        highlighted: impl IntoIterator<Item = (usize, usize)>,
+    ) -> std::io::Result<()> {
+        let mut canvas = Canvas::new(self.width, self.height, 4);
+        canvas.fill(|x, y| {
+            let Rgb(r, g, b) = Rgb::from_key(self.grid[y * self.width + x] as u32);
+            Rgb(r / 3, g / 3, b / 3)
+        });
+        canvas.overlay(highlighted, Rgb::YELLOW);
+        canvas.save(path)
+    }
+}
+
 impl Display for Wordsearch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.height {
@@ -311,3 +439,379 @@ impl Display for Wordsearch {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_match_word_search() {
+        let grid = Wordsearch::new(include_str!("../test_data.txt"));
+
+        let found = [Template::line("XMAS"), Template::diagonal("XMAS")]
+            .iter()
+            .flat_map(|template| grid.find_template(template, Orientations::All))
+            .count();
+        assert_eq!(found, 18);
+        assert_eq!(found, grid.find_words("XMAS").len());
+
+        let found = grid.find_template(&Template::new(X_MAS), Orientations::All);
+        assert_eq!(found.len(), 9);
+        assert_eq!(found.len(), grid.find_x_words("MAS").len());
+    }
+
+    #[test]
+    fn test_dictionary_matches_word_search() {
+        let grid = Wordsearch::new(include_str!("../test_data.txt"));
+        let dictionary = Dictionary::new(["XMAS", "MAS", "AM"]);
+        let found = grid.find_dictionary_words(&dictionary, Report::All);
+
+        for word in ["XMAS", "MAS", "AM"] {
+            let mut expected = grid
+                .find_words(word)
+                .iter()
+                .map(|found| (found.start, found.end))
+                .collect::<Vec<_>>();
+            let mut actual = found
+                .iter()
+                .filter(|found| found.word == word)
+                .map(|found| (found.start, found.end))
+                .collect::<Vec<_>>();
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_dictionary_report_once() {
+        let grid = Wordsearch::new("ABAQ\nXMAS\n");
+        let dictionary = Dictionary::new(["ABA", "XMAS", "MAS", "A"]);
+
+        let found = grid.find_dictionary_words(&dictionary, Report::All);
+        let count = |word: &str| found.iter().filter(|found| found.word == word).count();
+        assert_eq!((count("ABA"), count("XMAS"), count("MAS")), (2, 1, 1));
+
+        // The palindrome once, and MAS and every A are part of longer words.
+        let found = grid.find_dictionary_words(&dictionary, Report::Once);
+        let mut words = found
+            .iter()
+            .map(|found| (found.word.as_str(), found.start))
+            .collect::<Vec<_>>();
+        words.sort();
+        assert_eq!(words, vec![("ABA", (0, 0)), ("XMAS", (0, 1))]);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let grid = Wordsearch::new(include_str!("../test_data.txt"));
+
+        let words = grid.find_words("XMAS");
+        let cells = words.iter().flat_map(FoundWord::cells);
+        assert_eq!(
+            grid.highlight(cells, false).to_string(),
+            "....XXMAS.\n\
+             .SAMXMS...\n\
+             ...S..A...\n\
+             ..A.A.MS.X\n\
+             XMASAMX.MM\n\
+             X.....XA.A\n\
+             S.S.S.S.SS\n\
+             .A.A.A.A.A\n\
+             ..M.M.M.MM\n\
+             .X.X.XMASX\n"
+        );
+
+        let found = grid.find_template(&Template::new(X_MAS), Orientations::All);
+        let cells = found.iter().flat_map(TemplateMatch::cells);
+        assert_eq!(
+            grid.highlight(cells, false).to_string(),
+            ".M.S......\n\
+             ..A..MSMS.\n\
+             .M.S.MAA..\n\
+             ..A.ASMSM.\n\
+             .M.S.M....\n\
+             ..........\n\
+             S.S.S.S.S.\n\
+             .A.A.A.A..\n\
+             M.M.M.M.M.\n\
+             ..........\n"
+        );
+
+        // Only the words read right to left.
+        let cells = words
+            .iter()
+            .filter(|word| word.direction == "left".parse().unwrap())
+            .flat_map(FoundWord::cells);
+        assert_eq!(
+            grid.highlight(cells, false).to_string().lines().nth(4),
+            Some("...SAMX...")
+        );
+        assert_eq!("Up-Left".parse(), Ok(Direction::UpLeft));
+    }
+
+    #[test]
+    fn test_orientations() {
+        let template = Template::new("AB\nC.");
+        let orientations = template.orientations(Orientations::All);
+        assert_eq!(orientations.len(), 8);
+        assert_eq!(orientations[1].to_string(), "CA\n.B\n");
+        assert_eq!(orientations[4].to_string(), "BA\n.C\n");
+
+        // Symmetric templates only appear once per distinct orientation.
+        assert_eq!(
+            Template::line("XMAS").orientations(Orientations::All).len(),
+            4
+        );
+        assert_eq!(
+            Template::line("ABA").orientations(Orientations::All).len(),
+            2
+        );
+        assert_eq!(
+            Template::new(X_MAS).orientations(Orientations::All).len(),
+            4
+        );
+        assert_eq!(template.orientations(Orientations::Exact), vec![template]);
+    }
+
+    #[test]
+    fn test_template_anchors() {
+        let grid = Wordsearch::new("ABC\nDAB\nCDA\n");
+        let found = grid.find_template(&Template::new("AB"), Orientations::Exact);
+        assert_eq!(
+            found.iter().map(|found| found.anchor).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1)]
+        );
+        assert_eq!(found[1].cells(), vec![(1, 1), (2, 1)]);
+
+        // Templates bigger than the grid never match.
+        let found = grid.find_template(&Template::line("ABCD"), Orientations::All);
+        assert!(found.is_empty());
+    }
+
+    // A small xorshift generator, seeded so that any failure can be repeated.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn letters(&mut self, length: usize) -> String {
+            (0..length)
+                .map(|_| b"XMAS"[self.below(4)] as char)
+                .collect()
+        }
+
+        // Anything from a single cell to 8x8, usually not square, and half
+        // of them wrapping round.
+        fn grid(&mut self) -> Wordsearch {
+            let (width, height) = (1 + self.below(8), 1 + self.below(8));
+            let rows = (0..height).map(|_| self.letters(width)).collect::<Vec<_>>();
+            let edges = [Edges::Bounded, Edges::Wrap][self.below(2)];
+            Wordsearch::new(&rows.join("\n")).with_edges(edges)
+        }
+    }
+
+    // Off a bounded grid there's nothing, while a wrapped one repeats forever.
+    fn position(grid: &Wordsearch, x: isize, y: isize) -> Option<(usize, usize)> {
+        let (width, height) = (grid.width as isize, grid.height as isize);
+        match grid.edges {
+            Edges::Bounded => ((0..width).contains(&x) && (0..height).contains(&y))
+                .then_some((x as usize, y as usize)),
+            Edges::Wrap => Some((x.rem_euclid(width) as usize, y.rem_euclid(height) as usize)),
+        }
+    }
+
+    fn letter_at(grid: &Wordsearch, x: isize, y: isize) -> Option<char> {
+        position(grid, x, y).map(|(x, y)| grid.grid[y * grid.width + x])
+    }
+
+    // Tries every start and direction, letter by letter.
+    fn naive_find_words(grid: &Wordsearch, word: &str) -> Vec<((usize, usize), (usize, usize))> {
+        let mut found = Vec::new();
+        for y in 0..grid.height as isize {
+            for x in 0..grid.width as isize {
+                for direction in Direction::iter() {
+                    let (dx, dy) = direction.delta();
+                    let matches = word.chars().enumerate().all(|(i, c)| {
+                        letter_at(grid, x + i as isize * dx, y + i as isize * dy) == Some(c)
+                    });
+                    if matches {
+                        let last = word.len() as isize - 1;
+                        let end = position(grid, x + last * dx, y + last * dy).unwrap();
+                        found.push(((x as usize, y as usize), end));
+                    }
+                }
+            }
+        }
+        found.sort();
+        found
+    }
+
+    // Reads both diagonals through every cell, checking them either way round.
+    fn naive_find_x_words(grid: &Wordsearch, word: &str) -> Vec<(usize, usize)> {
+        let reach = (word.len() as isize - 1) / 2;
+        let reversed = word.chars().rev().collect::<String>();
+        let mut found = Vec::new();
+        for y in 0..grid.height as isize {
+            for x in 0..grid.width as isize {
+                let diagonal = |dy: isize| {
+                    (-reach..=reach)
+                        .map(|i| letter_at(grid, x + i, y + i * dy))
+                        .collect::<Option<String>>()
+                };
+                let is_word = |diagonal: Option<String>| {
+                    diagonal.is_some_and(|diagonal| diagonal == word || diagonal == reversed)
+                };
+                if is_word(diagonal(1)) && is_word(diagonal(-1)) {
+                    found.push((x as usize, y as usize));
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn test_find_words_matches_reference() {
+        let mut rng = Rng(0x2024_0004);
+        for _ in 0..2000 {
+            let grid = rng.grid();
+            // Up to 10 letters, so often longer than the grid.
+            let length = 1 + rng.below(10);
+            let word = rng.letters(length);
+
+            let mut found = grid
+                .find_words(&word)
+                .iter()
+                .map(|found| (found.start, found.end))
+                .collect::<Vec<_>>();
+            found.sort();
+            assert_eq!(
+                found,
+                naive_find_words(&grid, &word),
+                "{} in\n{}",
+                word,
+                grid
+            );
+
+            // The dictionary search must find exactly the same words.
+            let words = |found: Vec<FoundWord>| {
+                let mut words = found
+                    .iter()
+                    .map(|found| {
+                        (
+                            found.start,
+                            found.end,
+                            found.direction as usize,
+                            found.cells(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                words.sort();
+                words
+            };
+            assert_eq!(
+                words(grid.find_dictionary_words(&Dictionary::new([&word]), Report::All)),
+                words(grid.find_words(&word)),
+                "{} in\n{}",
+                word,
+                grid
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_x_words_matches_reference() {
+        let mut rng = Rng(0x0004_2024);
+        for _ in 0..2000 {
+            let grid = rng.grid();
+            let length = 1 + 2 * rng.below(5);
+            let word = rng.letters(length);
+
+            let found = grid
+                .find_x_words(&word)
+                .iter()
+                .map(|found| found.start)
+                .collect::<Vec<_>>();
+            let expected = naive_find_x_words(&grid, &word);
+            assert_eq!(found, expected, "{} in\n{}", word, grid);
+
+            // Asking about any single cell, including those on the edges,
+            // must agree as well.
+            for y in 0..grid.height {
+                for x in 0..grid.width {
+                    assert_eq!(
+                        grid.find_x_word_at(x, y, &word).is_some(),
+                        expected.contains(&(x, y)),
+                        "{} at {},{} in\n{}",
+                        word,
+                        x,
+                        y,
+                        grid
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edges() {
+        // The bottom right corner is the centre of a wrapped-round X that
+        // must not be found.
+        let grid = Wordsearch::new("MXM\nXAX\nSXS\nXMX\n");
+        assert!(grid.find_x_word_at(2, 3, "MAS").is_none());
+        assert!(grid.find_x_word_at(1, 1, "MAS").is_some());
+
+        let grid = Wordsearch::new("X");
+        assert_eq!(grid.find_words("X").len(), 8);
+        assert!(grid.find_words("XMAS").is_empty());
+        assert!(grid.find_words("").is_empty());
+        assert!(grid.find_x_words("MAS").is_empty());
+        assert!(grid.find_x_words("XMASAMX").is_empty());
+    }
+
+    #[test]
+    fn test_wrap() {
+        let grid = Wordsearch::new("ASXM\nQQQQ");
+        assert!(grid.find_words("XMAS").is_empty());
+
+        let grid = grid.with_edges(Edges::Wrap);
+        let found = grid.find_words("XMAS");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].cells(), vec![(2, 0), (3, 0), (0, 0), (1, 0)]);
+        let found = grid.find_dictionary_words(&Dictionary::new(["XMAS"]), Report::All);
+        assert_eq!(found.len(), 1);
+        let found = grid.find_template(&Template::line("XMAS"), Orientations::All);
+        assert_eq!(found.len(), 1);
+
+        // An X centred in the top left corner, with its arms wrapping round.
+        let grid = Wordsearch::new("A..\n.SM\n.SM\n");
+        assert!(grid.find_x_words("MAS").is_empty());
+
+        let grid = grid.with_edges(Edges::Wrap);
+        let found = grid.find_x_words("MAS");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].start, (0, 0));
+        let found = grid.find_template(&Template::new(X_MAS), Orientations::All);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].anchor, (2, 2));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Wordsearch::parse("\n \n").err(), Some(ParseError::Empty));
+        assert_eq!(
+            Wordsearch::parse("XMAS\nXMA\n").err(),
+            Some(ParseError::RaggedRow {
+                y: 1,
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(Wordsearch::parse("ÄÖ\nXM").map(|grid| grid.width), Ok(2));
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use meaningful_lines::MeaningfulLines;
+
+/// A grid of letters read from text: one row per (non-blank) line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Letters {
+    pub grid: Vec<char>,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    RaggedRow {
+        y: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "The wordsearch is empty"),
+            ParseError::RaggedRow { y, expected, found } => write!(
+                f,
+                "Row {} is {} letters wide, but the first row is {}",
+                y, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Reads the rows of letters, which must all be the same width.  Widths are
+/// counted in characters rather than bytes, so any letters will do.
+pub fn parse(text: &str) -> Result<Letters, ParseError> {
+    let mut grid = Vec::new();
+    let mut width = None;
+    let mut height = 0;
+
+    for (y, line) in MeaningfulLines::new(text).enumerate() {
+        let found = line.chars().count();
+        let expected = *width.get_or_insert(found);
+        if found != expected {
+            return Err(ParseError::RaggedRow { y, expected, found });
+        }
+
+        grid.extend(line.chars());
+        height += 1;
+    }
+
+    let width = width.ok_or(ParseError::Empty)?;
+    Ok(Letters {
+        grid,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use crate::Wordsearch;
+
+const HIGHLIGHT_COLOUR: &str = "\x1b[1;33m";
+const RESET: &str = "\x1b[0m";
+
+/// The grid with only the letters of found words showing, as in the puzzle's
+/// illustrations.  Every other cell is drawn as `.`.
+pub struct Highlighted<'a> {
+    wordsearch: &'a Wordsearch,
+    covered: Vec<bool>,
+    colour: bool,
+}
+
+impl Wordsearch {
+    /// Renders the grid showing only the given cells, in colour if asked
+    /// (e.g. when writing to a terminal).
+    pub fn highlight(
+        &self,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        colour: bool,
+    ) -> Highlighted<'_> {
+        let mut covered = vec![false; self.width * self.height];
+        for (x, y) in cells {
+            covered[y * self.width + x] = true;
+        }
+
+        Highlighted {
+            wordsearch: self,
+            covered,
+            colour,
+        }
+    }
+}
+
+impl Display for Highlighted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grid = self.wordsearch;
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let index = y * grid.width + x;
+                if !self.covered[index] {
+                    write!(f, ".")?;
+                } else if self.colour {
+                    write!(f, "{}{}{}", HIGHLIGHT_COLOUR, grid.grid[index], RESET)?;
+                } else {
+                    write!(f, "{}", grid.grid[index])?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
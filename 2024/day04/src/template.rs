@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use grid_edges::Edges;
+
+use crate::Wordsearch;
+
+/// A 2D pattern of letters to look for in a wordsearch, where `None` cells
+/// match any letter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Option<char>>,
+}
+
+/// Whether to also look for a template turned around and/or mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientations {
+    /// Only the template as given.
+    Exact,
+
+    /// All 4 rotations of the template and of its mirror image.
+    All,
+}
+
+/// A placement of a template in the grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateMatch {
+    /// The grid position of the template's top left cell.
+    pub anchor: (usize, usize),
+
+    /// The template as it was found, i.e. after any rotation or reflection.
+    pub template: Template,
+
+    // The grid cells covered by the template's letters.
+    cells: Vec<(usize, usize)>,
+}
+
+impl Template {
+    /// Parses a template from lines of text, where `.` matches any letter.
+    /// Shorter lines are padded with wildcards.
+    pub fn new(pattern: &str) -> Self {
+        let lines = pattern
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let height = lines.len();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .expect("A template needs at least one line");
+
+        let mut cells = Vec::with_capacity(width * height);
+        for line in lines {
+            let mut row = line
+                .chars()
+                .map(|c| if c == '.' { None } else { Some(c) })
+                .collect::<Vec<_>>();
+            row.resize(width, None);
+            cells.extend(row);
+        }
+
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// A word written left to right.
+    pub fn line(word: &str) -> Self {
+        Self {
+            width: word.chars().count(),
+            height: 1,
+            cells: word.chars().map(Some).collect(),
+        }
+    }
+
+    /// A word written diagonally from the top left to the bottom right.
+    pub fn diagonal(word: &str) -> Self {
+        let size = word.chars().count();
+        let mut cells = vec![None; size * size];
+        for (i, c) in word.chars().enumerate() {
+            cells[i * size + i] = Some(c);
+        }
+        Self {
+            width: size,
+            height: size,
+            cells,
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.cells[y * self.width + x]
+    }
+
+    // The template turned a quarter turn clockwise.
+    fn rotate(&self) -> Self {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.width {
+            for x in 0..self.height {
+                cells.push(self.get(y, self.height - 1 - x));
+            }
+        }
+        Self {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    // The template mirrored left to right.
+    fn reflect(&self) -> Self {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                cells.push(self.get(x, y));
+            }
+        }
+        Self {
+            width: self.width,
+            height: self.height,
+            cells,
+        }
+    }
+
+    /// The distinct templates to search for.  Symmetric templates look the
+    /// same in several orientations and only appear once, so that each
+    /// placement is found once.
+    pub fn orientations(&self, orientations: Orientations) -> Vec<Template> {
+        let mut templates = vec![self.clone()];
+        if orientations == Orientations::All {
+            for start in [self.clone(), self.reflect()] {
+                let mut template = start;
+                for _ in 0..4 {
+                    if !templates.contains(&template) {
+                        templates.push(template.clone());
+                    }
+                    template = template.rotate();
+                }
+            }
+        }
+        templates
+    }
+
+    // The positions of the template's letters relative to its top left
+    // corner, with the letters.
+    fn letters(&self) -> impl Iterator<Item = ((isize, isize), char)> + '_ {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Some(((x as isize, y as isize), self.get(x, y)?)))
+    }
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                write!(f, "{}", self.get(x, y).unwrap_or('.'))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl TemplateMatch {
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        self.cells.clone()
+    }
+}
+
+impl Wordsearch {
+    /// Every placement of the template (in any of the orientations asked for)
+    /// where all of its letters match the grid.
+    pub fn find_template(
+        &self,
+        template: &Template,
+        orientations: Orientations,
+    ) -> Vec<TemplateMatch> {
+        let mut matches = Vec::new();
+        for template in template.orientations(orientations) {
+            // On a bounded grid the template has to fit; on a wrapped one it
+            // can go over the edges, and even overlap itself.
+            let (width, height) = match self.edges {
+                Edges::Bounded if template.width > self.width || template.height > self.height => {
+                    continue;
+                }
+                Edges::Bounded => (
+                    self.width - template.width + 1,
+                    self.height - template.height + 1,
+                ),
+                Edges::Wrap => (self.width, self.height),
+            };
+
+            for y in 0..height {
+                for x in 0..width {
+                    if let Some(cells) = self.template_matches_at(&template, (x, y)) {
+                        matches.push(TemplateMatch {
+                            anchor: (x, y),
+                            template: template.clone(),
+                            cells,
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    // The cells the template's letters cover if they all match.
+    fn template_matches_at(
+        &self,
+        template: &Template,
+        anchor: (usize, usize),
+    ) -> Option<Vec<(usize, usize)>> {
+        template
+            .letters()
+            .map(|((x, y), c)| {
+                let (x, y) = self.edges.offset(anchor, (x, y), 1, self.size())?;
+                (self.grid[y * self.width + x] == c).then_some((x, y))
+            })
+            .collect()
+    }
+}
@@ -1,21 +1,116 @@
-#![allow(unused)]
+mod partial_order;
 
-use std::mem::swap;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
+use dot::Graph;
 use meaningful_lines::FileSections;
+use partial_order::PartialOrder;
 
 fn main() {
+    // Export the rules as a Graphviz graph (--dot FILE), optionally just those
+    // between the pages of one update (--update N).
+    if let Some(path) = arg_value("--dot") {
+        let data = load_data();
+        let update = arg_value("--update").map(|index| {
+            let index: usize = index.parse().expect("Invalid update number");
+            data.updates
+                .get(index)
+                .unwrap_or_else(|| panic!("There is no update {}", index))
+                .as_slice()
+        });
+        std::fs::write(&path, data.rules_graph(update).to_string())
+            .unwrap_or_else(|error| panic!("Unable to write {}: {}", path, error));
+        println!("Wrote the rules to {}", path);
+        return;
+    }
+
+    // Ask the rules whether one page must come before another
+    // (--precedes A,B).
+    if let Some(pair) = arg_value("--precedes") {
+        let (a, b) = pair
+            .split_once(',')
+            .map(|(a, b)| (a.parse(), b.parse()))
+            .and_then(|pages| Some((pages.0.ok()?, pages.1.ok()?)))
+            .unwrap_or_else(|| panic!("--precedes needs two pages, e.g. 47,53"));
+        let order = load_data().partial_order();
+        if order.directly_precedes(a, b) {
+            println!("{} must come before {} (rule {}|{})", a, b, a, b);
+        } else if order.precedes(a, b) {
+            println!("{} must come before {} (through a chain of rules)", a, b);
+        } else {
+            println!("Nothing requires {} to come before {}", a, b);
+        }
+        return;
+    }
+
+    // Count the ways one update's pages can be ordered, and find the fewest
+    // rules that force its corrected order (--orderings N).
+    if let Some(index) = arg_value("--orderings") {
+        let data = load_data();
+        let index: usize = index.parse().expect("Invalid update number");
+        let pages = data
+            .updates
+            .get(index)
+            .unwrap_or_else(|| panic!("There is no update {}", index));
+        data.explain_orderings(index, pages);
+        return;
+    }
+
     part1();
     part2();
 }
 
+// Loads the puzzle input, or the worked example with --example.
+fn load_data() -> Data {
+    if std::env::args().any(|arg| arg == "--example") {
+        Data::new(include_str!("../test.txt"))
+    } else {
+        Data::new(include_str!("../data.txt"))
+    }
+}
+
+// Returns the value following a command line flag, e.g. `--dot rules.dot`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    Some(
+        args.next()
+            .unwrap_or_else(|| panic!("{} needs a value", name)),
+    )
+}
+
 fn part1() {
-    let data = Data::new(include_str!("../data.txt"));
-    let updates: u32 = data
-        .apply_rules()
+    let data = load_data();
+    let results = data.apply_rules();
+
+    // Show why each update was accepted or rejected (--explain).
+    if std::env::args().any(|arg| arg == "--explain") {
+        for (i, (pages, result)) in data.updates.iter().zip(&results).enumerate() {
+            let pages = pages
+                .iter()
+                .map(|page| page.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            match result {
+                Update::ValidUpdate(_) => println!("Update {} ({}): valid", i, pages),
+                Update::InvalidUpdate(_, violations) => {
+                    println!("Update {} ({}): invalid", i, pages);
+                    for violation in violations {
+                        println!("    violates {}", violation);
+                    }
+                }
+            }
+        }
+    }
+
+    let updates: u32 = results
         .iter()
         .filter_map(|update| match update {
-            Update::InvalidUpdate(_) => None,
+            Update::InvalidUpdate(..) => None,
             Update::ValidUpdate(page) => Some(page),
         })
         .sum();
@@ -24,39 +119,171 @@ fn part1() {
 }
 
 fn part2() {
-    let data = Data::new(include_str!("../data.txt"));
-    let updates = data
+    let data = load_data();
+    let orders = data
         .apply_rules()
         .iter()
-        .filter(|update| match update {
-            Update::InvalidUpdate(_) => true,
-            Update::ValidUpdate(_) => false,
-        })
-        .map(|update| match update {
-            Update::InvalidUpdate(pages) => data.correct_pages(pages),
-            Update::ValidUpdate(_) => panic!("Invalid update"),
+        .filter_map(|update| match update {
+            Update::InvalidUpdate(pages, _) => Some(data.correct_pages(pages)),
+            Update::ValidUpdate(_) => None,
         })
-        .map(|pages| pages[pages.len() / 2])
+        .collect::<Vec<_>>();
+
+    // Updates whose rules contradict each other can't be ordered, so report
+    // them and leave them out.
+    for cycle in orders.iter().filter_map(|order| order.as_ref().err()) {
+        eprintln!("Unable to order update: {}", cycle);
+    }
+
+    let orders = orders.iter().filter_map(|order| order.as_ref().ok());
+    let ambiguous = orders.clone().filter(|order| !order.unique).count();
+    let updates = orders
+        .map(|order| order.pages[order.pages.len() / 2])
         .sum::<u32>();
 
     println!("Part 2: {}", updates);
+    if ambiguous > 0 {
+        println!("({} updates have more than one valid order)", ambiguous);
+    }
 }
 
 #[derive(Debug)]
 struct Data {
     ordering_rules: Vec<(u32, u32)>,
+    rules: RuleIndex,
     updates: Vec<Vec<u32>>,
 }
 
+// The ordering rules indexed for constant time lookup of any pair of pages.
+#[derive(Debug, Default)]
+struct RuleIndex {
+    before: HashSet<(u32, u32)>,
+}
+
+impl RuleIndex {
+    fn new(ordering_rules: &[(u32, u32)]) -> Self {
+        RuleIndex {
+            before: ordering_rules.iter().copied().collect(),
+        }
+    }
+
+    // Returns true if a rule says that `a` must come before `b`.
+    fn must_precede(&self, a: u32, b: u32) -> bool {
+        self.before.contains(&(a, b))
+    }
+
+    // A comparator for `sort_by`.  Pages without a rule between them compare
+    // as equal, so this is only a total order if the rules cover every pair
+    // without any cycles: check with `is_total_order` before sorting.
+    fn compare(&self, a: u32, b: u32) -> Ordering {
+        if self.must_precede(a, b) {
+            Ordering::Less
+        } else if self.must_precede(b, a) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    // Whether the rules put the pages in exactly one order, so that `compare`
+    // is a total order over them.  That needs a rule one way or the other
+    // between every pair of distinct pages, and no cycles.  With a rule
+    // between every pair, there are no cycles exactly when the pages must
+    // precede different numbers of the others (n - 1 for the first, down to
+    // 0 for the last), which saves following chains of rules.
+    fn is_total_order(&self, pages: &[u32]) -> bool {
+        let mut precedes = vec![0; pages.len()];
+        for (i, &a) in pages.iter().enumerate() {
+            for (j, &b) in pages.iter().enumerate().skip(i + 1) {
+                match (self.must_precede(a, b), self.must_precede(b, a)) {
+                    (true, false) => precedes[i] += 1,
+                    (false, true) => precedes[j] += 1,
+                    _ => return false,
+                }
+            }
+        }
+
+        precedes.sort_unstable();
+        precedes.iter().enumerate().all(|(i, &count)| i == count)
+    }
+
+    // Every rule broken by the pages, in the order the pages appear.
+    fn violations(&self, pages: &[u32]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (b_index, &b) in pages.iter().enumerate() {
+            for (a_index, &a) in pages.iter().enumerate().skip(b_index + 1) {
+                if self.must_precede(a, b) {
+                    violations.push(Violation {
+                        rule: (a, b),
+                        positions: (a_index, b_index),
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    // Checks every pair of pages against the rules: O(pages²) lookups.
+    fn is_ordered(&self, pages: &[u32]) -> bool {
+        pages
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| pages[i + 1..].iter().all(|&b| !self.must_precede(b, a)))
+    }
+}
+
 #[derive(Debug)]
 enum Update {
-    // The pages are invalid.  Data is the invalid pages
-    InvalidUpdate(Vec<u32>),
+    // The pages are invalid.  Data is the invalid pages and the rules they break
+    InvalidUpdate(Vec<u32>, Vec<Violation>),
 
     // The update pages are valid and the data is the middle page
     ValidUpdate(u32),
 }
 
+// A rule `a|b` broken by an update, with the positions in the update where
+// `a` and `b` were found (`b` comes first).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Violation {
+    rule: (u32, u32),
+    positions: (usize, usize),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ((a, b), (a_index, b_index)) = (self.rule, self.positions);
+        write!(
+            f,
+            "{}|{} ({} is at position {}, but {} is at position {})",
+            a, b, a, a_index, b, b_index
+        )
+    }
+}
+
+// An ordering of an update's pages that satisfies every rule.
+#[derive(Debug, PartialEq)]
+struct Order {
+    pages: Vec<u32>,
+
+    // Whether this is the only order that satisfies the rules.
+    unique: bool,
+}
+
+// Rules that contradict each other: each page must come before the next, and
+// the last before the first.
+#[derive(Debug, PartialEq)]
+struct Cycle(Vec<u32>);
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rules form a cycle ")?;
+        for page in &self.0 {
+            write!(f, "{} -> ", page)?;
+        }
+        write!(f, "{}", self.0[0])
+    }
+}
+
 impl Data {
     fn new(input: &str) -> Self {
         let mut sections = FileSections::new(input);
@@ -64,7 +291,7 @@ impl Data {
         let ordering_rules_input = sections.next().expect("No ordering rules");
         let update_input = sections.next().expect("No updates");
 
-        let ordering_rules = ordering_rules_input
+        let ordering_rules: Vec<(u32, u32)> = ordering_rules_input
             .lines()
             .map(|line| {
                 let mut parts = line.split('|');
@@ -92,6 +319,7 @@ impl Data {
             .collect();
 
         Self {
+            rules: RuleIndex::new(&ordering_rules),
             ordering_rules,
             updates,
         }
@@ -101,54 +329,534 @@ impl Data {
         self.updates
             .iter()
             .map(|update| {
-                let mut valid = true;
-                for (a, b) in &self.ordering_rules {
-                    let a_index = update.iter().position(|&x| x == *a);
-                    let b_index = update.iter().position(|&x| x == *b);
-
-                    if let (Some(a_index), Some(b_index)) = (a_index, b_index) {
-                        if a_index > b_index {
-                            valid = false;
-                            break;
-                        }
-                    }
-                }
-                if valid {
+                let violations = self.rules.violations(update);
+                if violations.is_empty() {
                     Update::ValidUpdate(update[update.len() / 2])
                 } else {
-                    Update::InvalidUpdate(update.clone())
+                    Update::InvalidUpdate(update.clone(), violations)
                 }
             })
             .collect()
     }
 
-    fn correct_pages(&self, updates: &Vec<u32>) -> Vec<u32> {
-        let mut pages = updates.clone();
-        let mut change_count = 1;
-        let mut iteration_count = 0;
+    fn partial_order(&self) -> PartialOrder {
+        PartialOrder::new(&self.ordering_rules)
+    }
 
-        while change_count > 0 {
-            change_count = 0;
+    // Prints how many valid orderings an update has and, if there's only
+    // one, the rules that force it.
+    fn explain_orderings(&self, index: usize, pages: &[u32]) {
+        let order = self.partial_order();
+        match order.count_orderings(pages) {
+            Ok(count) => println!("Update {} has {} valid ordering(s)", index, count),
+            Err(error) => println!("Unable to count orderings of update {}: {}", index, error),
+        }
 
-            for (a, b) in &self.ordering_rules {
-                let a_index = pages.iter().position(|&x| x == *a);
-                let b_index = pages.iter().position(|&x| x == *b);
+        let corrected = match self.correct_pages(pages) {
+            Ok(corrected) => corrected,
+            Err(cycle) => {
+                println!("Unable to order update: {}", cycle);
+                return;
+            }
+        };
+        let pages = corrected
+            .pages
+            .iter()
+            .map(|page| page.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        match order.minimal_rules(&corrected.pages) {
+            Some(rules) => {
+                let rules = rules
+                    .iter()
+                    .map(|(a, b)| format!("{}|{}", a, b))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!("The order {} is forced by the rules {}", pages, rules);
+            }
+            None => println!("The rules don't force the order {}", pages),
+        }
+    }
 
-                if let (Some(a_index), Some(b_index)) = (a_index, b_index) {
-                    if a_index > b_index {
-                        let elem = pages.remove(a_index);
-                        pages.insert(b_index, elem);
-                        change_count += 1;
-                    }
+    // The rules as a graph, with an edge from `a` to `b` for every rule `a|b`.
+    // Given an update, only its pages are included, and the rules it breaks
+    // are drawn in red.  Every rule on a cycle, which makes it impossible to
+    // order the pages, is drawn thicker in orange.
+    fn rules_graph(&self, update: Option<&[u32]>) -> Graph {
+        let pages = match update {
+            Some(pages) => pages.to_vec(),
+            None => {
+                let mut pages = self
+                    .ordering_rules
+                    .iter()
+                    .flat_map(|&(a, b)| [a, b])
+                    .collect::<Vec<_>>();
+                pages.sort();
+                pages.dedup();
+                pages
+            }
+        };
+        let violated = update
+            .map(|pages| self.rules.violations(pages))
+            .unwrap_or_default()
+            .iter()
+            .map(|violation| violation.rule)
+            .collect::<HashSet<_>>();
+        // An edge is on a cycle exactly when both its pages are in the same
+        // strongly connected component.
+        let component = self
+            .cyclic_components(&pages)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, component)| component.into_iter().map(move |page| (page, i)))
+            .collect::<HashMap<_, _>>();
+        let in_cycle = |a: u32, b: u32| {
+            component
+                .get(&a)
+                .is_some_and(|i| component.get(&b) == Some(i))
+        };
+
+        let mut graph = Graph::new("rules");
+        for &page in &pages {
+            graph.node(page);
+        }
+        for &(a, b) in &self.ordering_rules {
+            if !pages.contains(&a) || !pages.contains(&b) {
+                continue;
+            }
+            let edge = graph.edge(a, b);
+            if in_cycle(a, b) {
+                edge.attr("color", "orange").attr("penwidth", 3);
+            }
+            if violated.contains(&(a, b)) {
+                edge.attr("color", "red");
+            }
+        }
+        graph
+    }
+
+    // The groups of pages that the rules between them put on cycles, i.e.
+    // the strongly connected components of more than one page (or of a page
+    // with a rule about itself).  Uses Tarjan's algorithm.
+    fn cyclic_components(&self, pages: &[u32]) -> Vec<Vec<u32>> {
+        let mut tarjan = Tarjan {
+            rules: &self.rules,
+            pages,
+            index: HashMap::new(),
+            low_link: HashMap::new(),
+            stack: Vec::new(),
+            components: Vec::new(),
+        };
+        for &page in pages {
+            if !tarjan.index.contains_key(&page) {
+                tarjan.visit(page);
+            }
+        }
+
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.rules.must_precede(component[0], component[0])
+            })
+            .collect()
+    }
+
+    // Puts the pages in an order that satisfies the rules.  Normally the
+    // rules give a total order for an update's pages and a single sort does
+    // it, and the order is unique.  Otherwise the comparator isn't a total
+    // order, which `sort_by` may panic on, so the slower topological sort
+    // takes over.  It can handle gaps in the rules and find cycles in them.
+    fn correct_pages(&self, pages: &[u32]) -> Result<Order, Cycle> {
+        if !self.rules.is_total_order(pages) {
+            return self.topological_order(pages);
+        }
+
+        let mut sorted = pages.to_vec();
+        sorted.sort_by(|&a, &b| self.rules.compare(a, b));
+        debug_assert!(self.rules.is_ordered(&sorted));
+
+        Ok(Order {
+            pages: sorted,
+            unique: true,
+        })
+    }
+
+    // Orders the pages with a topological sort (Kahn's algorithm) of the rules
+    // that mention two of them.  If the sort ever has a choice of which page to
+    // take next, the order isn't unique.  A page listed more than once is only
+    // placed once.
+    fn topological_order(&self, pages: &[u32]) -> Result<Order, Cycle> {
+        let mut seen = HashSet::new();
+        let pages = pages
+            .iter()
+            .copied()
+            .filter(|&page| seen.insert(page))
+            .collect::<Vec<_>>();
+
+        let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut in_degree: HashMap<u32, usize> = pages.iter().map(|&page| (page, 0)).collect();
+
+        for &a in &pages {
+            for &b in &pages {
+                if self.rules.must_precede(a, b) {
+                    successors.entry(a).or_default().push(b);
+                    *in_degree.get_mut(&b).unwrap() += 1;
                 }
             }
+        }
 
-            iteration_count += 1;
-            if iteration_count > self.ordering_rules.len() {
-                panic!("Unable to solve constraints");
+        // When there's a choice, take the page that came first in the update,
+        // so the result is deterministic and moves as little as possible.
+        let mut ready = pages
+            .iter()
+            .copied()
+            .filter(|page| in_degree[page] == 0)
+            .collect::<Vec<_>>();
+        let mut order = Vec::with_capacity(pages.len());
+        let mut unique = true;
+
+        while !ready.is_empty() {
+            unique &= ready.len() == 1;
+
+            let next = (0..ready.len())
+                .min_by_key(|&i| pages.iter().position(|&page| page == ready[i]))
+                .unwrap();
+            let page = ready.swap_remove(next);
+            order.push(page);
+
+            for next in successors.get(&page).into_iter().flatten() {
+                let degree = in_degree.get_mut(next).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(*next);
+                }
             }
         }
 
-        pages
+        if order.len() < pages.len() {
+            return Err(Self::find_cycle(&successors, &in_degree));
+        }
+
+        Ok(Order {
+            pages: order,
+            unique,
+        })
+    }
+
+    // Finds a cycle among the pages a topological sort couldn't place.  Each of
+    // them still has a predecessor that wasn't placed, so following those
+    // backwards must eventually revisit a page.
+    fn find_cycle(successors: &HashMap<u32, Vec<u32>>, in_degree: &HashMap<u32, usize>) -> Cycle {
+        let remaining = |page: &u32| in_degree[page] > 0;
+        let predecessor = |page: u32| {
+            successors
+                .iter()
+                .filter(|(from, to)| remaining(from) && to.contains(&page))
+                .map(|(&from, _)| from)
+                .min()
+                .expect("Unplaced page without an unplaced predecessor")
+        };
+
+        let start = in_degree
+            .keys()
+            .copied()
+            .filter(remaining)
+            .min()
+            .expect("No unplaced pages");
+        let mut walk = vec![start];
+        let mut page = predecessor(start);
+        while !walk.contains(&page) {
+            walk.push(page);
+            page = predecessor(page);
+        }
+
+        // The walk went backwards, so the cycle runs the other way.  Start it
+        // from its smallest page so that it's always reported the same way.
+        let first = walk.iter().position(|&p| p == page).unwrap();
+        let mut cycle = walk.split_off(first);
+        cycle.reverse();
+        let smallest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+        cycle.rotate_left(smallest);
+        Cycle(cycle)
+    }
+}
+
+// The state of a depth first search for strongly connected components.
+struct Tarjan<'a> {
+    rules: &'a RuleIndex,
+    pages: &'a [u32],
+
+    // The order pages were first visited in, and the earliest visited page
+    // each can reach through pages that are still on the stack.
+    index: HashMap<u32, usize>,
+    low_link: HashMap<u32, usize>,
+
+    // Visited pages not yet assigned to a component.
+    stack: Vec<u32>,
+    components: Vec<Vec<u32>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, page: u32) {
+        let index = self.index.len();
+        self.index.insert(page, index);
+        self.low_link.insert(page, index);
+        self.stack.push(page);
+
+        for &next in self.pages {
+            if !self.rules.must_precede(page, next) {
+                continue;
+            }
+            if !self.index.contains_key(&next) {
+                self.visit(next);
+                let low_link = self.low_link[&page].min(self.low_link[&next]);
+                self.low_link.insert(page, low_link);
+            } else if self.stack.contains(&next) {
+                let low_link = self.low_link[&page].min(self.index[&next]);
+                self.low_link.insert(page, low_link);
+            }
+        }
+
+        // A page that can't reach anything visited before it is the root of
+        // a component, made up of it and everything above it on the stack.
+        if self.low_link[&page] == index {
+            let start = self.stack.iter().rposition(|&p| p == page).unwrap();
+            self.components.push(self.stack.split_off(start));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use partial_order::CountError;
+
+    #[test]
+    fn test_correct_pages() {
+        let data = Data::new(include_str!("../test.txt"));
+
+        let expected = Ok(Order {
+            pages: vec![97, 75, 47, 29, 13],
+            unique: true,
+        });
+        assert_eq!(data.correct_pages(&[97, 13, 75, 29, 47]), expected);
+        assert_eq!(data.topological_order(&[97, 13, 75, 29, 47]), expected);
+    }
+
+    #[test]
+    fn test_sort_matches_topological_order() {
+        let data = Data::new(include_str!("../test.txt"));
+
+        for update in &data.updates {
+            let sorted = data.correct_pages(update).unwrap();
+            assert!(data.rules.is_ordered(&sorted.pages));
+            assert_eq!(Ok(sorted), data.topological_order(update));
+        }
+    }
+
+    #[test]
+    fn test_violations() {
+        let data = Data::new(include_str!("../test.txt"));
+
+        // The example explains that this one breaks the rule 97|75.
+        assert_eq!(
+            data.rules.violations(&[75, 97, 47, 61, 53]),
+            vec![Violation {
+                rule: (97, 75),
+                positions: (1, 0)
+            }]
+        );
+        assert_eq!(data.rules.violations(&[75, 47, 61, 53, 29]), vec![]);
+        assert_eq!(
+            data.rules
+                .violations(&[97, 13, 75, 29, 47])
+                .iter()
+                .map(|violation| violation.rule)
+                .collect::<Vec<_>>(),
+            vec![(75, 13), (29, 13), (47, 13), (47, 29)]
+        );
+    }
+
+    #[test]
+    fn test_rules_graph() {
+        let data = Data::new(include_str!("../test.txt"));
+
+        let graph = data.rules_graph(Some(&[61, 13, 29])).to_string();
+        assert_eq!(
+            graph,
+            "digraph \"rules\" {\n    \
+                \"61\";\n    \
+                \"13\";\n    \
+                \"29\";\n    \
+                \"61\" -> \"13\";\n    \
+                \"29\" -> \"13\" [color=\"red\"];\n    \
+                \"61\" -> \"29\";\n\
+            }\n"
+        );
+
+        let data = Data::new("1|2\n2|3\n3|1\n3|4\n\n1,2,3,4\n");
+        let graph = data.rules_graph(None).to_string();
+        assert_eq!(
+            graph.lines().filter(|line| line.contains("orange")).count(),
+            3
+        );
+        assert!(graph.contains("\"3\" -> \"4\";"));
+
+        // Two separate cycles, the second with an extra rule across it, and a
+        // rule joining them that isn't on either.
+        let data = Data::new("1|2\n2|1\n2|3\n3|4\n4|5\n5|3\n3|5\n\n1,2,3,4,5\n");
+        let mut components = data.cyclic_components(&[1, 2, 3, 4, 5]);
+        for component in &mut components {
+            component.sort();
+        }
+        components.sort();
+        assert_eq!(components, vec![vec![1, 2], vec![3, 4, 5]]);
+
+        let graph = data.rules_graph(None).to_string();
+        let orange = graph
+            .lines()
+            .filter(|line| line.contains("orange"))
+            .collect::<Vec<_>>();
+        assert_eq!(orange.len(), 6);
+        assert!(graph.contains("\"2\" -> \"3\";"));
+    }
+
+    #[test]
+    fn test_partial_order() {
+        let data = Data::new(include_str!("../test.txt"));
+        let order = data.partial_order();
+
+        assert!(order.directly_precedes(47, 53));
+        assert!(!order.directly_precedes(53, 47));
+        assert!(order.precedes(97, 13));
+        assert!(!order.precedes(13, 97));
+
+        // Every update in the example has exactly one valid order.
+        for pages in &data.updates {
+            assert_eq!(order.count_orderings(pages), Ok(1));
+        }
+        assert_eq!(
+            order.minimal_rules(&[97, 75, 47, 29, 13]),
+            Some(vec![(97, 75), (75, 47), (47, 29), (29, 13)])
+        );
+        assert_eq!(order.minimal_rules(&[75, 97, 47, 61, 53]), None);
+
+        // 1 before 2 and 3, which can go either way round, 4 unrelated to
+        // anything, and 5 and 6 in a cycle.
+        let order = PartialOrder::new(&[(1, 2), (1, 3), (5, 6), (6, 5)]);
+        assert!(!order.precedes(2, 3) && !order.precedes(3, 2));
+        assert!(order.precedes(5, 5));
+        assert_eq!(order.count_orderings(&[1, 2, 3]), Ok(2));
+        assert_eq!(order.count_orderings(&[1, 2, 3, 4]), Ok(8));
+        assert_eq!(order.count_orderings(&[5, 6]), Ok(0));
+        assert_eq!(order.minimal_rules(&[1, 2, 3]), None);
+        assert_eq!(order.minimal_rules(&[3, 1]), None);
+    }
+
+    #[test]
+    fn test_count_orderings_limits() {
+        let pages = (0..65).collect::<Vec<u32>>();
+        assert_eq!(
+            PartialOrder::new(&[]).count_orderings(&pages),
+            Err(CountError::PageLimit(65))
+        );
+
+        // 30 unrelated pages can go in 30! orders, which would mean looking
+        // at all 2^30 sets of them.
+        assert_eq!(
+            PartialOrder::new(&[]).count_orderings(&pages[..30]),
+            Err(CountError::Unconstrained)
+        );
+
+        // A chain of 64 pages only ever has one choice.
+        let chain = pages[..64]
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            PartialOrder::new(&chain).count_orderings(&pages[..64]),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_order() {
+        let data = Data::new("1|2\n1|3\n\n3,2,1\n");
+
+        let order = data.correct_pages(&[3, 2, 1]).unwrap();
+        assert_eq!(order.pages, vec![1, 3, 2]);
+        assert!(!order.unique);
+    }
+
+    // A cycle among a long update's pages, shuffled in many ways.  Sorting
+    // pages like these with the comparator can panic, as it isn't a total
+    // order.
+    #[test]
+    fn test_long_cycle() {
+        // Every page before every later one, except that the last comes
+        // before the first.
+        let pages = (1..=30).collect::<Vec<u32>>();
+        let mut rules = Vec::new();
+        for &a in &pages {
+            for &b in &pages[a as usize..] {
+                rules.push(if (a, b) == (1, 30) { (b, a) } else { (a, b) });
+            }
+        }
+        let data = Data {
+            rules: RuleIndex::new(&rules),
+            ordering_rules: rules,
+            updates: vec![],
+        };
+
+        let mut state = 0x2024_0005_u64;
+        let mut shuffled = pages.clone();
+        for _ in 0..200 {
+            for i in (1..shuffled.len()).rev() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                shuffled.swap(i, (state % (i as u64 + 1)) as usize);
+            }
+
+            assert!(!data.rules.is_total_order(&shuffled));
+            assert!(data.correct_pages(&shuffled).is_err());
+        }
+    }
+
+    #[test]
+    fn test_total_order() {
+        let data = Data::new(include_str!("../test.txt"));
+
+        assert!(data.rules.is_total_order(&[97, 13, 75, 29, 47]));
+        assert!(!data.rules.is_total_order(&[97, 13, 97]));
+        assert_eq!(
+            data.correct_pages(&[13, 97, 13]).unwrap().pages,
+            vec![97, 13]
+        );
+
+        let data = Data::new("1|2\n2|3\n3|1\n\n1,2,3\n");
+        assert!(!data.rules.is_total_order(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_repeated_page() {
+        let data = Data::new("1|2\n2|3\n\n3,2,1,2\n");
+
+        let expected = Ok(Order {
+            pages: vec![1, 2, 3],
+            unique: true,
+        });
+        assert_eq!(data.topological_order(&[3, 2, 1, 2]), expected);
+        assert_eq!(data.topological_order(&[2, 2]).unwrap().pages, vec![2]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let data = Data::new("5|1\n1|2\n2|3\n3|1\n\n3,2,1,5\n");
+
+        let cycle = data.correct_pages(&[3, 2, 1, 5]).unwrap_err();
+        assert_eq!(cycle, Cycle(vec![1, 2, 3]));
+        assert_eq!(cycle.to_string(), "rules form a cycle 1 -> 2 -> 3 -> 1");
     }
 }
@@ -0,0 +1,184 @@
+use std::{
+    cell::OnceCell,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+// Counting orderings works through the sets of pages that can be placed
+// first.  Rules that leave few choices keep these few, but with every page
+// free there are 2^n of them, so the count gives up after this many.
+const MAX_PLACED_SETS: usize = 100_000;
+
+/// The order the rules put pages in, for asking questions about the rules
+/// themselves rather than about a particular update.
+///
+/// The puzzle's rules aren't necessarily a true partial order: taken as a
+/// whole they may contain cycles, and only the rules between the pages of an
+/// update apply to it.  `precedes` follows chains of rules through any page,
+/// so pages on a cycle precede each other (and themselves).  The questions
+/// about a set of pages only use the rules between those pages, just as
+/// checking an update does.
+#[derive(Debug)]
+pub struct PartialOrder {
+    successors: HashMap<u32, HashSet<u32>>,
+
+    // Every page reachable from each page, worked out the first time it's
+    // needed.
+    closure: OnceCell<HashMap<u32, HashSet<u32>>>,
+}
+
+impl PartialOrder {
+    pub fn new(ordering_rules: &[(u32, u32)]) -> Self {
+        let mut successors: HashMap<u32, HashSet<u32>> = HashMap::new();
+        for &(a, b) in ordering_rules {
+            successors.entry(a).or_default().insert(b);
+        }
+
+        Self {
+            successors,
+            closure: OnceCell::new(),
+        }
+    }
+
+    /// Is there a rule `a|b`?
+    pub fn directly_precedes(&self, a: u32, b: u32) -> bool {
+        self.successors
+            .get(&a)
+            .is_some_and(|after| after.contains(&b))
+    }
+
+    /// Must `a` come before `b`, either because of a rule `a|b` or a chain of
+    /// rules `a|x`, `x|y`, ..., `z|b`?
+    pub fn precedes(&self, a: u32, b: u32) -> bool {
+        self.closure()
+            .get(&a)
+            .is_some_and(|after| after.contains(&b))
+    }
+
+    fn closure(&self) -> &HashMap<u32, HashSet<u32>> {
+        self.closure.get_or_init(|| {
+            self.successors
+                .keys()
+                .map(|&page| (page, self.reachable_from(page)))
+                .collect()
+        })
+    }
+
+    // A depth first search along the rules.  The page itself is only included
+    // if it's on a cycle.
+    fn reachable_from(&self, page: u32) -> HashSet<u32> {
+        let mut reachable = HashSet::new();
+        let mut stack = vec![page];
+        while let Some(page) = stack.pop() {
+            for &next in self.successors.get(&page).into_iter().flatten() {
+                if reachable.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// The number of ways the pages can be ordered without breaking any of the
+    /// rules between them.  This is 0 if those rules contain a cycle.
+    ///
+    /// The work grows exponentially with the number of pages the rules leave
+    /// free to go in either order, so loosely constrained pages fail with
+    /// `CountError::Unconstrained` rather than taking forever.
+    pub fn count_orderings(&self, pages: &[u32]) -> Result<u128, CountError> {
+        if pages.len() > 64 {
+            return Err(CountError::PageLimit(pages.len()));
+        }
+
+        // The pages that must come before each page, as a bit mask.
+        let before = pages
+            .iter()
+            .map(|&b| {
+                pages
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &a)| self.directly_precedes(a, b))
+                    .fold(0u64, |mask, (i, _)| mask | 1 << i)
+            })
+            .collect::<Vec<_>>();
+
+        let mut memo = HashMap::new();
+        Self::count_from(0, &before, &mut memo)
+    }
+
+    // Counts the orderings of the pages not yet placed.
+    fn count_from(
+        placed: u64,
+        before: &[u64],
+        memo: &mut HashMap<u64, u128>,
+    ) -> Result<u128, CountError> {
+        if placed.count_ones() as usize == before.len() {
+            return Ok(1);
+        }
+        if let Some(&count) = memo.get(&placed) {
+            return Ok(count);
+        }
+        if memo.len() >= MAX_PLACED_SETS {
+            return Err(CountError::Unconstrained);
+        }
+
+        let mut count = 0u128;
+        for i in 0..before.len() {
+            if placed & 1 << i == 0 && before[i] & !placed == 0 {
+                let more = Self::count_from(placed | 1 << i, before, memo)?;
+                count = count.checked_add(more).ok_or(CountError::Overflow)?;
+            }
+        }
+
+        memo.insert(placed, count);
+        Ok(count)
+    }
+
+    /// The fewest rules that on their own only allow the pages in this order.
+    ///
+    /// Each neighbouring pair of pages needs a rule of its own, since a chain
+    /// of rules between them would have to pass through a page placed between
+    /// them.  Those rules are also enough, so they're the answer if they all
+    /// exist.  Returns `None` if the order breaks a rule or the rules don't
+    /// force it.
+    pub fn minimal_rules(&self, order: &[u32]) -> Option<Vec<(u32, u32)>> {
+        let breaks_rule = order
+            .iter()
+            .enumerate()
+            .any(|(i, &b)| order[i + 1..].iter().any(|&a| self.directly_precedes(a, b)));
+        if breaks_rule {
+            return None;
+        }
+
+        order
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .map(|(a, b)| self.directly_precedes(a, b).then_some((a, b)))
+            .collect()
+    }
+}
+
+/// Why the orderings of some pages couldn't be counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountError {
+    /// More pages than can be tracked, which is 64.
+    PageLimit(usize),
+
+    /// The rules leave too many pages free to be placed in any order.
+    Unconstrained,
+
+    /// There are more orderings than fit in a `u128`.
+    Overflow,
+}
+
+impl Display for CountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CountError::PageLimit(pages) => {
+                write!(f, "{} pages is too many, the limit is 64", pages)
+            }
+            CountError::Unconstrained => write!(f, "the rules leave too many choices to count"),
+            CountError::Overflow => write!(f, "there are too many orderings to count"),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use simulate::Step;
+
+use crate::{rules::Turn, Guard, GuardDirection, Map, MapCell};
+
+const DIRECTIONS: [GuardDirection; 4] = [
+    GuardDirection::Up,
+    GuardDirection::Down,
+    GuardDirection::Left,
+    GuardDirection::Right,
+];
+
+/// For every cell and direction, the cell where the guard would stop walking
+/// because the next one is an obstacle, or `None` if they'd walk off the map.
+/// This lets the guard teleport from turn to turn rather than stepping one
+/// cell at a time.  Only valid for guards who leave at the edge of the map.
+pub struct JumpTable {
+    width: usize,
+    height: usize,
+    turn: Turn,
+    stops: [Vec<Option<usize>>; 4],
+}
+
+impl JumpTable {
+    pub fn new(map: &Map) -> Self {
+        let (width, height) = (map.width, map.height);
+        let mut stops = DIRECTIONS.map(|_| vec![None; width * height]);
+
+        for direction in DIRECTIONS {
+            let (dx, dy) = direction.into();
+            let stops = &mut stops[direction as usize];
+
+            // Visit the cells so that the one ahead of the guard has always
+            // been filled in already.
+            let xs = if dx > 0 {
+                (0..width).rev().collect::<Vec<_>>()
+            } else {
+                (0..width).collect()
+            };
+            let ys = if dy > 0 {
+                (0..height).rev().collect::<Vec<_>>()
+            } else {
+                (0..height).collect()
+            };
+
+            for &y in &ys {
+                for &x in &xs {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || nx >= width as isize || ny < 0 || ny >= height as isize {
+                        continue;
+                    }
+
+                    let next = ny as usize * width + nx as usize;
+                    stops[y * width + x] = if map.data[next] == MapCell::Obstacle {
+                        Some(y * width + x)
+                    } else {
+                        stops[next]
+                    };
+                }
+            }
+        }
+
+        JumpTable {
+            width,
+            height,
+            turn: map.rules.turn,
+            stops,
+        }
+    }
+
+    /// Moves the guard to the next place they turn (and turns them), taking an
+    /// extra obstacle into account that isn't in the table.  Halts if the guard
+    /// walks off the map instead.
+    pub fn step(&self, guard: &Guard, extra_obstacle: Option<(usize, usize)>) -> Step<Guard> {
+        let (x, y, direction) = *guard;
+
+        // How far the guard can walk before the table's obstacle...
+        let table_distance = self.stops[direction as usize][y * self.width + x]
+            .map(|stop| (stop % self.width).abs_diff(x) + (stop / self.width).abs_diff(y));
+
+        // ...and before the extra one, if it's in the way.
+        let extra_distance = extra_obstacle.and_then(|(ox, oy)| match direction {
+            GuardDirection::Up => (ox == x && oy < y).then(|| y - oy - 1),
+            GuardDirection::Down => (ox == x && oy > y).then(|| oy - y - 1),
+            GuardDirection::Left => (oy == y && ox < x).then(|| x - ox - 1),
+            GuardDirection::Right => (oy == y && ox > x).then(|| ox - x - 1),
+        });
+
+        let distance = match (table_distance, extra_distance) {
+            (None, None) => return Step::Halt,
+            (Some(a), Some(b)) => a.min(b),
+            (Some(a), None) | (None, Some(a)) => a,
+        };
+
+        let (dx, dy) = direction.into();
+        let nx = (x as isize + dx * distance as isize) as usize;
+        let ny = (y as isize + dy * distance as isize) as usize;
+        debug_assert!(nx < self.width && ny < self.height);
+
+        Step::Continue((nx, ny, self.turn.apply(direction)))
+    }
+}
@@ -1,14 +1,104 @@
+mod jump;
+mod rules;
+mod visualize;
+mod what_if;
+
 use std::fmt::Display;
 
+use jump::JumpTable;
 use meaningful_lines::MeaningfulLines;
+use parallel::parallel_map;
+use rules::{Edges, Rules};
+use simulate::{simulate, simulate_brent, Step};
 
 fn main() {
+    if let Some(directory) = arg_value("--frames") {
+        let map = load_map();
+        let every = arg_value("--every").map_or(1, |every| every.parse().expect("Invalid --every"));
+        let count = visualize::export_frames(
+            &map,
+            obstacle_from_args(),
+            std::path::Path::new(&directory),
+            every,
+        )
+        .expect("Unable to write frames");
+        println!("Wrote {} frames to {}", count, directory);
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--visualize") {
+        let options = visualize::Options {
+            fps: arg_value("--fps").map_or(30, |fps| fps.parse().expect("Invalid frame rate")),
+            obstacle: obstacle_from_args(),
+        };
+
+        let map = load_map();
+        if let Some((x, y)) = options.obstacle {
+            println!(
+                "Obstacle at {},{} causes a loop: {}",
+                x,
+                y,
+                map.check_obstacle(x, y)
+            );
+        }
+        visualize::visualize(&map, &options).expect("Unable to write to stdout");
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--what-if") {
+        let map = load_map();
+        for what_if in map.what_if(parallel::jobs_from_args()) {
+            println!(
+                "Obstacle at {},{}: loop of {} steps",
+                what_if.obstacle.0,
+                what_if.obstacle.1,
+                what_if.cycle().len()
+            );
+            println!("{}", map.render_what_if(&what_if));
+        }
+        return;
+    }
+
     part1();
     part2();
 }
 
+// Loads the puzzle input, or the worked example with --example.
+fn load_map() -> Map {
+    let text = if std::env::args().any(|arg| arg == "--example") {
+        include_str!("../test.txt")
+    } else {
+        include_str!("../data.txt")
+    };
+
+    Map::new(text, Rules::from_args()).unwrap_or_else(|error| panic!("Invalid map: {}", error))
+}
+
+// An extra obstacle to place on the map (--obstacle x,y).
+fn obstacle_from_args() -> Option<(usize, usize)> {
+    arg_value("--obstacle").map(|position| {
+        let (x, y) = position
+            .split_once(',')
+            .expect("Obstacle position should be x,y");
+        (
+            x.parse().expect("Invalid obstacle x"),
+            y.parse().expect("Invalid obstacle y"),
+        )
+    })
+}
+
+// Returns the value following a command line flag, e.g. `--fps 60`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    Some(
+        args.next()
+            .unwrap_or_else(|| panic!("{} needs a value", name)),
+    )
+}
+
 fn part1() {
-    let mut map = Map::new(include_str!("../data.txt"));
+    let mut map = load_map();
     map.generate_path();
 
     println!(
@@ -21,23 +111,16 @@ fn part1() {
 }
 
 fn part2() {
-    let map = Map::new(include_str!("../data.txt"));
+    let map = load_map();
 
-    let mut count = 0;
+    let count = map.loop_obstacles(parallel::jobs_from_args()).len();
 
-    for y in 0..map.height {
-        for x in 0..map.width {
-            if x == map.guard.0 && y == map.guard.1 {
-                continue;
-            }
-            if map.check_obstacle(x, y) {
-                count += 1;
-            }
-        }
+    // The fast search makes a lot of assumptions, so allow it to be checked
+    // against trying every cell.
+    if std::env::args().any(|arg| arg == "--check") {
+        assert_eq!(count, map.brute_force_loop_obstacles().len());
     }
 
-    // map.check_obstacle(3, 6);
-
     println!("The answer is {}", count);
 }
 
@@ -49,15 +132,7 @@ enum MapCell {
     Path,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-struct Route {
-    moved_up: bool,
-    moved_down: bool,
-    moved_left: bool,
-    moved_right: bool,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum GuardDirection {
     Up,
     Down,
@@ -65,6 +140,32 @@ enum GuardDirection {
     Right,
 }
 
+impl GuardDirection {
+    fn glyph(self) -> char {
+        match self {
+            GuardDirection::Up => '^',
+            GuardDirection::Down => 'V',
+            GuardDirection::Left => '<',
+            GuardDirection::Right => '>',
+        }
+    }
+
+    fn turn_right(self) -> Self {
+        match self {
+            GuardDirection::Up => GuardDirection::Right,
+            GuardDirection::Down => GuardDirection::Left,
+            GuardDirection::Left => GuardDirection::Up,
+            GuardDirection::Right => GuardDirection::Down,
+        }
+    }
+}
+
+// Position and facing of the guard.
+type Guard = (usize, usize, GuardDirection);
+
+// Every guard still on the map, as they move in lockstep.
+type Guards = Vec<Guard>;
+
 impl From<GuardDirection> for (isize, isize) {
     fn from(direction: GuardDirection) -> Self {
         match direction {
@@ -81,9 +182,54 @@ struct Map {
     width: usize,
     height: usize,
     data: Vec<MapCell>,
-    guard: (usize, usize, GuardDirection),
+    guards: Guards,
+    rules: Rules,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MapError {
+    Empty,
+    RaggedRow {
+        y: usize,
+        expected: usize,
+        found: usize,
+    },
+    UnknownGlyph {
+        x: usize,
+        y: usize,
+        glyph: char,
+    },
+    NoGuard,
+    MultipleGuards {
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+}
+
+impl Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "The map is empty"),
+            MapError::RaggedRow { y, expected, found } => write!(
+                f,
+                "Row {} is {} cells wide, but the first row is {}",
+                y, found, expected
+            ),
+            MapError::UnknownGlyph { x, y, glyph } => {
+                write!(f, "Unknown glyph '{}' at {},{}", glyph, x, y)
+            }
+            MapError::NoGuard => write!(f, "There is no guard on the map"),
+            MapError::MultipleGuards { first, second } => write!(
+                f,
+                "There is more than one guard: at {},{} and at {},{}",
+                first.0, first.1, second.0, second.1
+            ),
+        }
+    }
 }
 
+impl std::error::Error for MapError {}
+
 impl Display for MapCell {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let c = match self {
@@ -99,14 +245,8 @@ impl Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.height {
             for x in 0..self.width {
-                if x == self.guard.0 && y == self.guard.1 {
-                    let c = match self.guard.2 {
-                        GuardDirection::Up => '^',
-                        GuardDirection::Down => 'V',
-                        GuardDirection::Left => '<',
-                        GuardDirection::Right => '>',
-                    };
-                    write!(f, "{}", c)?;
+                if let Some(guard) = self.guards.iter().find(|g| (g.0, g.1) == (x, y)) {
+                    write!(f, "{}", guard.2.glyph())?;
                     continue;
                 }
                 let cell = &self.data[y * self.width + x];
@@ -119,137 +259,381 @@ impl Display for Map {
 }
 
 impl Map {
-    fn new(text: &str) -> Self {
-        let width = text.lines().next().unwrap().len();
+    // Parses the map, insisting that every row is the same width, that there
+    // is exactly one guard (or at least one in lockstep) and that every glyph
+    // is known.
+    fn new(text: &str, rules: Rules) -> Result<Self, MapError> {
+        let width = MeaningfulLines::new(text)
+            .next()
+            .ok_or(MapError::Empty)?
+            .chars()
+            .count();
         let height = MeaningfulLines::new(text).count();
 
-        let mut map = Map {
+        let mut data = Vec::with_capacity(width * height);
+        let mut guards: Guards = Vec::new();
+
+        for (y, line) in MeaningfulLines::new(text).enumerate() {
+            let found = line.chars().count();
+            if found != width {
+                return Err(MapError::RaggedRow {
+                    y,
+                    expected: width,
+                    found,
+                });
+            }
+
+            for (x, c) in line.chars().enumerate() {
+                let direction = match c {
+                    '.' => None,
+                    '#' => {
+                        data.push(MapCell::Obstacle);
+                        continue;
+                    }
+                    '^' => Some(GuardDirection::Up),
+                    'V' => Some(GuardDirection::Down),
+                    '<' => Some(GuardDirection::Left),
+                    '>' => Some(GuardDirection::Right),
+                    _ => return Err(MapError::UnknownGlyph { x, y, glyph: c }),
+                };
+
+                if let Some(direction) = direction {
+                    if let (Some(&(first_x, first_y, _)), false) = (guards.first(), rules.lockstep)
+                    {
+                        return Err(MapError::MultipleGuards {
+                            first: (first_x, first_y),
+                            second: (x, y),
+                        });
+                    }
+                    guards.push((x, y, direction));
+                }
+                data.push(MapCell::Empty);
+            }
+        }
+
+        if guards.is_empty() {
+            return Err(MapError::NoGuard);
+        }
+
+        Ok(Map {
             width,
             height,
-            data: Vec::with_capacity(width * height),
-            guard: (0, 0, GuardDirection::Up),
+            data,
+            guards,
+            rules,
+        })
+    }
+
+    // Works out where the guard goes from the given position: one step
+    // forward, or a turn if that cell is blocked, according to the rules.
+    // The optional extra obstacle is treated as if it were on the map.
+    fn step(&self, guard: &Guard, extra_obstacle: Option<(usize, usize)>) -> Step<Guard> {
+        let (x, y, direction) = *guard;
+
+        let size = (self.width, self.height);
+        let Some((nx, ny)) = self.rules.edges.step((x, y), direction.into(), size) else {
+            return Step::Halt;
         };
-        map.data.resize_with(width * height, || MapCell::Empty);
-
-        let lines = MeaningfulLines::new(text);
-        lines.enumerate().for_each(|(y, line)| {
-            line.chars().enumerate().for_each(|(x, c)| {
-                let cell = match c {
-                    '.' => MapCell::Empty,
-                    '#' => MapCell::Obstacle,
-                    '^' => {
-                        map.guard = (x, y, GuardDirection::Up);
-                        MapCell::Empty
-                    }
-                    'V' => {
-                        map.guard = (x, y, GuardDirection::Down);
-                        MapCell::Empty
-                    }
-                    '<' => {
-                        map.guard = (x, y, GuardDirection::Left);
-                        MapCell::Empty
-                    }
-                    '>' => {
-                        map.guard = (x, y, GuardDirection::Right);
-                        MapCell::Empty
-                    }
-                    _ => panic!("Invalid character in map: {}", c),
-                };
-                map.data[y * width + x] = cell;
-            });
-        });
 
-        map
+        if self.data[ny * self.width + nx] == MapCell::Obstacle || extra_obstacle == Some((nx, ny))
+        {
+            Step::Continue((x, y, self.rules.turn.apply(direction)))
+        } else {
+            Step::Continue((nx, ny, direction))
+        }
+    }
+
+    // Moves every guard one step.  Guards who leave the map are dropped, and
+    // the walk is over once they have all gone.
+    fn step_all(&self, guards: &Guards, extra_obstacle: Option<(usize, usize)>) -> Step<Guards> {
+        let guards = guards
+            .iter()
+            .filter_map(|guard| match self.step(guard, extra_obstacle) {
+                Step::Continue(guard) => Some(guard),
+                Step::Halt => None,
+            })
+            .collect::<Guards>();
+
+        if guards.is_empty() {
+            Step::Halt
+        } else {
+            Step::Continue(guards)
+        }
     }
 
     fn generate_path(&mut self) {
-        loop {
-            let (x, y, direction) = self.guard;
+        let simulation = simulate(self.guards.clone(), |guards| self.step_all(guards, None));
 
+        for &(x, y, _) in simulation.trace.iter().flatten() {
             self.data[y * self.width + x] = MapCell::Path;
+        }
+        if let Some(guards) = simulation.trace.last() {
+            self.guards = guards.clone();
+        }
+    }
 
-            let (dx, dy) = direction.into();
-            let (nx, ny) = (x as isize + dx, y as isize + dy);
-            if nx < 0 || nx >= self.width as isize || ny < 0 || ny >= self.height as isize {
-                break;
-            }
-            let (nx, ny) = (nx as usize, ny as usize);
-            let cell = &mut self.data[ny * self.width + nx];
-            if *cell == MapCell::Obstacle {
-                // Turn right
-                let direction = match direction {
-                    GuardDirection::Up => GuardDirection::Right,
-                    GuardDirection::Down => GuardDirection::Left,
-                    GuardDirection::Left => GuardDirection::Up,
-                    GuardDirection::Right => GuardDirection::Down,
-                };
-                self.guard = (x, y, direction);
-            } else {
-                self.guard = (nx, ny, direction);
+    // Returns true if placing an obstacle at the given position makes the
+    // guards walk in a loop.
+    fn check_obstacle(&self, obstacle_x: usize, obstacle_y: usize) -> bool {
+        self.loops_from(&self.guards, (obstacle_x, obstacle_y))
+    }
+
+    // Returns true if the guards, starting in the given state, walk in a loop
+    // with an extra obstacle on the map.
+    fn loops_from(&self, guards: &Guards, obstacle: (usize, usize)) -> bool {
+        match guards.as_slice() {
+            // A lone guard is cheaper to simulate without the vector.
+            [guard] => simulate_brent(*guard, |guard| self.step(guard, Some(obstacle))).is_loop(),
+            _ => simulate_brent(guards.clone(), |guards| {
+                self.step_all(guards, Some(obstacle))
+            })
+            .is_loop(),
+        }
+    }
+
+    fn is_guard_start(&self, x: usize, y: usize) -> bool {
+        self.guards.iter().any(|guard| (guard.0, guard.1) == (x, y))
+    }
+
+    // Tries an obstacle on every empty cell of the map.
+    fn brute_force_loop_obstacles(&self) -> Vec<(usize, usize)> {
+        let mut obstacles = Vec::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_guard_start(x, y) || self.data[y * self.width + x] == MapCell::Obstacle {
+                    continue;
+                }
+                if self.check_obstacle(x, y) {
+                    obstacles.push((x, y));
+                }
             }
         }
+
+        obstacles
     }
 
-    fn check_obstacle(&self, obstacle_x: usize, obstacle_y: usize) -> bool {
-        let mut routes = Vec::with_capacity(self.width * self.height);
-        routes.resize_with(self.width * self.height, Route::default);
-
-        // Get position and direction of guard.
-        let (mut x, mut y, mut direction) = self.guard;
-
-        // Repeat movements until we leave the map or the guard moves in a loop.
-        loop {
-            // Find the direction for the guard to move in.
-            let (mut dx, mut dy) = direction.into();
-            let (mut nx, mut ny) = (x as isize + dx, y as isize + dy);
-
-            loop {
-                // If the new position is outside the map, we have found a bad obstacle.
-                if nx < 0 || nx >= self.width as isize || ny < 0 || ny >= self.height as isize {
-                    return false;
+    // Finds the same obstacles as `brute_force_loop_obstacles` but much faster.
+    // An obstacle off the guard's original path can't change it, so only
+    // those cells are tried, and each trial starts from the step just before
+    // the guard first reaches the cell, since everything before is unchanged.
+    // A single guard who can walk off the map jumps from turn to turn using a
+    // `JumpTable`.  The trials are shared out over `jobs` threads.
+    // If the guards loop even without a new obstacle (which some rules
+    // allow), every empty cell off their path keeps them looping too.
+    fn loop_obstacles(&self, jobs: usize) -> Vec<(usize, usize)> {
+        let simulation = simulate(self.guards.clone(), |guards| self.step_all(guards, None));
+        let trace = simulation.trace;
+
+        let mut tried = vec![false; self.width * self.height];
+        for &(x, y, _) in &self.guards {
+            tried[y * self.width + x] = true;
+        }
+
+        // Each trial is the guards' state before the candidate obstacle.
+        let mut trials = Vec::new();
+        for pair in trace.windows(2) {
+            for &(x, y, _) in &pair[1] {
+                if tried[y * self.width + x] {
+                    continue;
                 }
+                tried[y * self.width + x] = true;
+                trials.push((pair[0].clone(), (x, y)));
+            }
+        }
+
+        let jumps = (self.guards.len() == 1 && self.rules.edges == Edges::Bounded)
+            .then(|| JumpTable::new(self));
 
-                // Otherwise, if the new position is not an obstacle, we have
-                // found our good direction.
-                if self.data[ny as usize * self.width + nx as usize] != MapCell::Obstacle
-                    && (nx as usize != obstacle_x || ny as usize != obstacle_y)
-                {
-                    break;
+        parallel_map(&trials, jobs, |(guards, obstacle)| {
+            let looped = match &jumps {
+                Some(jumps) => {
+                    simulate_brent(guards[0], |guard| jumps.step(guard, Some(*obstacle))).is_loop()
                 }
+                None => self.loops_from(guards, *obstacle),
+            };
+            looped.then_some(*obstacle)
+        })
+        .into_iter()
+        .flatten()
+        .chain(
+            (0..self.width * self.height)
+                .filter(|&index| {
+                    simulation.outcome.is_loop()
+                        && !tried[index]
+                        && self.data[index] != MapCell::Obstacle
+                })
+                .map(|index| (index % self.width, index / self.width)),
+        )
+        .collect()
+    }
+}
 
-                // Otherwise, turn right.
-                direction = match direction {
-                    GuardDirection::Up => GuardDirection::Right,
-                    GuardDirection::Down => GuardDirection::Left,
-                    GuardDirection::Left => GuardDirection::Up,
-                    GuardDirection::Right => GuardDirection::Down,
-                };
+#[cfg(test)]
+mod tests {
+    use rules::Turn;
+
+    use super::*;
+
+    #[test]
+    fn test_fast_search_matches_brute_force() {
+        let map = Map::new(include_str!("../test.txt"), Rules::default()).unwrap();
+
+        let mut fast = map.loop_obstacles(4);
+        fast.sort();
+        let mut brute_force = map.brute_force_loop_obstacles();
+        brute_force.sort();
 
-                (dx, dy) = direction.into();
-                (nx, ny) = (x as isize + dx, y as isize + dy);
+        assert_eq!(fast.len(), 6);
+        assert_eq!(fast, brute_force);
+    }
+
+    #[test]
+    fn test_rules_fast_search_matches_brute_force() {
+        let example = include_str!("../test.txt");
+        // A second guard for the lockstep runs.
+        let two_guards = example.replacen("........#.", "..>.....#.", 1);
+
+        for turn in [Turn::Right, Turn::Left, Turn::Around] {
+            for edges in [Edges::Bounded, Edges::Wrap] {
+                for (text, lockstep) in [(example, false), (two_guards.as_str(), true)] {
+                    let rules = Rules {
+                        turn,
+                        edges,
+                        lockstep,
+                    };
+                    let map = Map::new(text, rules).unwrap();
+
+                    let mut fast = map.loop_obstacles(2);
+                    fast.sort();
+                    let mut brute_force = map.brute_force_loop_obstacles();
+                    brute_force.sort();
+
+                    assert_eq!(fast, brute_force, "{:?}", rules);
+                }
             }
+        }
+    }
 
-            // If the guard has already walked from this cell in this direction,
-            // we have found a good obstacle.
-            if match direction {
-                GuardDirection::Up => routes[y * self.width + x].moved_up,
-                GuardDirection::Down => routes[y * self.width + x].moved_down,
-                GuardDirection::Left => routes[y * self.width + x].moved_left,
-                GuardDirection::Right => routes[y * self.width + x].moved_right,
-            } {
-                return true;
+    #[test]
+    fn test_what_if_matches_example() {
+        let map = Map::new(include_str!("../test.txt"), Rules::default()).unwrap();
+        let what_ifs = map.what_if(1);
+
+        let mut obstacles = what_ifs
+            .iter()
+            .map(|what_if| what_if.obstacle)
+            .collect::<Vec<_>>();
+        obstacles.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(
+            obstacles,
+            vec![(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)]
+        );
+
+        // The first and last options from the puzzle text.
+        let what_if = what_ifs.iter().find(|w| w.obstacle == (3, 6)).unwrap();
+        assert_eq!(
+            map.render_what_if(what_if),
+            "\
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+"
+        );
+        let what_if = what_ifs.iter().find(|w| w.obstacle == (7, 9)).unwrap();
+        assert_eq!(
+            map.render_what_if(what_if),
+            "\
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+..+-+-+#|.
+..|.|.|.|.
+.#+-^-+-+.
+.+----++#.
+#+----++..
+......#O..
+"
+        );
+
+        let what_if = what_ifs.iter().find(|w| w.obstacle == (3, 6)).unwrap();
+        assert!(what_if
+            .cycle()
+            .iter()
+            .flatten()
+            .all(|&(x, y, _)| (4..=8).contains(&x) && (1..=6).contains(&y)));
+    }
+
+    #[test]
+    fn test_valid_map() {
+        let map = Map::new("\n..#\n.>.\n...\n", Rules::default()).unwrap();
+
+        assert_eq!((map.width, map.height), (3, 3));
+        assert_eq!(map.guards, vec![(1, 1, GuardDirection::Right)]);
+        assert_eq!(map.data[2], MapCell::Obstacle);
+    }
+
+    #[test]
+    fn test_empty_map() {
+        assert_eq!(Map::new("", Rules::default()).unwrap_err(), MapError::Empty);
+        assert_eq!(
+            Map::new("\n  \n", Rules::default()).unwrap_err(),
+            MapError::Empty
+        );
+    }
+
+    #[test]
+    fn test_ragged_rows() {
+        assert_eq!(
+            Map::new("...\n.^\n...", Rules::default()).unwrap_err(),
+            MapError::RaggedRow {
+                y: 1,
+                expected: 3,
+                found: 2
             }
+        );
+        assert_eq!(
+            Map::new("...\n.^.\n....", Rules::default()).unwrap_err(),
+            MapError::RaggedRow {
+                y: 2,
+                expected: 3,
+                found: 4
+            }
+        );
+    }
 
-            // Otherwise, let's mark the cell as visited and move the guard.
-            match direction {
-                GuardDirection::Up => routes[y * self.width + x].moved_up = true,
-                GuardDirection::Down => routes[y * self.width + x].moved_down = true,
-                GuardDirection::Left => routes[y * self.width + x].moved_left = true,
-                GuardDirection::Right => routes[y * self.width + x].moved_right = true,
+    #[test]
+    fn test_guard_count() {
+        assert_eq!(
+            Map::new("...\n.#.\n...", Rules::default()).unwrap_err(),
+            MapError::NoGuard
+        );
+        assert_eq!(
+            Map::new("^..\n...\n..<", Rules::default()).unwrap_err(),
+            MapError::MultipleGuards {
+                first: (0, 0),
+                second: (2, 2)
             }
+        );
+    }
 
-            x = nx as usize;
-            y = ny as usize;
-        }
+    #[test]
+    fn test_unknown_glyph() {
+        assert_eq!(
+            Map::new("...\n.^.\n.x.", Rules::default()).unwrap_err(),
+            MapError::UnknownGlyph {
+                x: 1,
+                y: 2,
+                glyph: 'x'
+            }
+        );
     }
 }
@@ -0,0 +1,63 @@
+use crate::GuardDirection;
+
+/// What happens when a guard walks off the edge of the map: with `Bounded`
+/// they leave and the walk is over, with `Wrap` they reappear on the opposite
+/// edge, so they can never leave.
+pub use grid_edges::Edges;
+
+/// What a guard does when the cell in front of them is blocked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+    #[default]
+    Right,
+    Left,
+    Around,
+}
+
+impl Turn {
+    pub fn apply(self, direction: GuardDirection) -> GuardDirection {
+        match self {
+            Turn::Right => direction.turn_right(),
+            Turn::Left => direction.turn_right().turn_right().turn_right(),
+            Turn::Around => direction.turn_right().turn_right(),
+        }
+    }
+}
+
+/// The movement policy shared by every simulation of the guards.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub turn: Turn,
+    pub edges: Edges,
+
+    /// Allow several guards on the map.  They all move at the same time, one
+    /// step per tick, but don't block each other.
+    pub lockstep: bool,
+}
+
+impl Rules {
+    /// Reads the rules from the command line: `--turn left|right|around`,
+    /// `--wrap` and `--lockstep`.  Anything not given keeps the puzzle's rules.
+    pub fn from_args() -> Self {
+        let mut rules = Rules::default();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--turn" => {
+                    rules.turn = match args.next().as_deref() {
+                        Some("right") => Turn::Right,
+                        Some("left") => Turn::Left,
+                        Some("around") => Turn::Around,
+                        other => panic!("Invalid turn: {:?}", other),
+                    }
+                }
+                "--wrap" => rules.edges = Edges::Wrap,
+                "--lockstep" => rules.lockstep = true,
+                _ => {}
+            }
+        }
+
+        rules
+    }
+}
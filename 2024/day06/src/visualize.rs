@@ -0,0 +1,195 @@
+use std::{
+    io::{IsTerminal, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use grid_image::{Canvas, Format, FrameWriter, Rgb};
+use simulate::{simulate, Outcome};
+
+use crate::{Guard, Map, MapCell};
+
+// ANSI escape sequences used to redraw the map in place.
+const CLEAR_SCREEN: &str = "\x1b[2J";
+const CURSOR_HOME: &str = "\x1b[H";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const RESET: &str = "\x1b[0m";
+const GUARD_COLOUR: &str = "\x1b[1;32m";
+const PATH_COLOUR: &str = "\x1b[33m";
+const LOOP_COLOUR: &str = "\x1b[1;31m";
+const OBSTACLE_COLOUR: &str = "\x1b[1;36m";
+
+pub struct Options {
+    /// Frames per second when animating on a terminal.
+    pub fps: u32,
+
+    /// An extra obstacle to place before walking, to watch the loop it causes.
+    pub obstacle: Option<(usize, usize)>,
+}
+
+/// Walks the guards across the map one step at a time, drawing each step.  On
+/// a terminal the map is redrawn in place; otherwise every frame is written
+/// out in turn so that the output can be piped somewhere.
+pub fn visualize(map: &Map, options: &Options) -> std::io::Result<()> {
+    if let Some((x, y)) = options.obstacle {
+        assert!(x < map.width && y < map.height, "Obstacle is off the map");
+        assert!(!map.is_guard_start(x, y), "Obstacle is on a guard");
+    }
+
+    let simulation = simulate(map.guards.clone(), |guards| {
+        map.step_all(guards, options.obstacle)
+    });
+    let loop_start = match simulation.outcome {
+        Outcome::Loop { start, .. } => Some(start),
+        Outcome::Halted { .. } => None,
+    };
+
+    let animate = std::io::stdout().is_terminal();
+    let delay = Duration::from_secs_f64(1.0 / options.fps.max(1) as f64);
+
+    let mut visited = vec![false; map.width * map.height];
+    let mut in_loop = vec![false; map.width * map.height];
+
+    let mut out = std::io::stdout().lock();
+    if animate {
+        write!(out, "{}{}", CLEAR_SCREEN, HIDE_CURSOR)?;
+    }
+
+    for (i, guards) in simulation.trace.iter().enumerate() {
+        for &(x, y, _) in guards {
+            visited[y * map.width + x] = true;
+            in_loop[y * map.width + x] |= loop_start.is_some_and(|start| i >= start);
+        }
+
+        let frame = Frame {
+            map,
+            visited: &visited,
+            in_loop: &in_loop,
+            guards,
+            obstacle: options.obstacle,
+            colour: animate,
+        };
+
+        if animate {
+            write!(out, "{}{}", CURSOR_HOME, frame)?;
+            out.flush()?;
+            thread::sleep(delay);
+        } else {
+            writeln!(out, "Step {}:\n{}", i, frame)?;
+        }
+    }
+
+    if animate {
+        write!(out, "{}", SHOW_CURSOR)?;
+    }
+
+    match simulation.outcome {
+        Outcome::Halted { length } => {
+            writeln!(out, "The guards left the map after {} steps", length - 1)
+        }
+        Outcome::Loop { start, period } => writeln!(
+            out,
+            "The guards loop forever: the loop starts at step {} and takes {} steps",
+            start, period
+        ),
+    }
+}
+
+/// Writes an image of every `every`th step of the guards' walk (and the final
+/// one) into a directory, so that they can be stitched into a video.  Returns
+/// the number of frames written.
+pub fn export_frames(
+    map: &Map,
+    obstacle: Option<(usize, usize)>,
+    directory: &Path,
+    every: usize,
+) -> std::io::Result<usize> {
+    let simulation = simulate(map.guards.clone(), |guards| map.step_all(guards, obstacle));
+    let loop_start = match simulation.outcome {
+        Outcome::Loop { start, .. } => Some(start),
+        Outcome::Halted { .. } => None,
+    };
+
+    let mut frames = FrameWriter::new(directory, Format::Png)?;
+    let mut canvas = Canvas::new(map.width, map.height, 4);
+    canvas.fill(|x, y| match map.data[y * map.width + x] {
+        MapCell::Obstacle => Rgb::WHITE,
+        MapCell::Empty | MapCell::Path => Rgb::BLACK,
+    });
+    canvas.overlay(obstacle, Rgb::BLUE);
+
+    let last = simulation.trace.len() - 1;
+    for (i, guards) in simulation.trace.iter().enumerate() {
+        // The canvas accumulates the path, with only a copy getting the guards.
+        let colour = if loop_start.is_some_and(|start| i >= start) {
+            Rgb::RED
+        } else {
+            Rgb::YELLOW
+        };
+        let cells = guards.iter().map(|&(x, y, _)| (x, y));
+        canvas.overlay(cells.clone(), colour);
+
+        if i % every.max(1) == 0 || i == last {
+            let mut frame = canvas.clone();
+            frame.mark(cells, Rgb::GREEN);
+            frames.write(&frame)?;
+        }
+    }
+
+    Ok(frames.frames_written())
+}
+
+// A single frame of the animation.  Without colour, cells on the loop are
+// drawn as `*` and the extra obstacle as `O`.
+struct Frame<'a> {
+    map: &'a Map,
+    visited: &'a [bool],
+    in_loop: &'a [bool],
+    guards: &'a [Guard],
+    obstacle: Option<(usize, usize)>,
+    colour: bool,
+}
+
+impl std::fmt::Display for Frame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.map.height {
+            for x in 0..self.map.width {
+                let index = y * self.map.width + x;
+
+                let guard = self
+                    .guards
+                    .iter()
+                    .find(|guard| (guard.0, guard.1) == (x, y));
+                let (c, colour) = if let Some(guard) = guard {
+                    (guard.2.glyph(), GUARD_COLOUR)
+                } else if self.obstacle == Some((x, y)) {
+                    ('O', OBSTACLE_COLOUR)
+                } else if self.in_loop[index] {
+                    if self.colour {
+                        ('X', LOOP_COLOUR)
+                    } else {
+                        ('*', LOOP_COLOUR)
+                    }
+                } else if self.visited[index] {
+                    ('X', PATH_COLOUR)
+                } else {
+                    let c = match self.map.data[index] {
+                        MapCell::Obstacle => '#',
+                        MapCell::Empty | MapCell::Path => '.',
+                    };
+                    (c, "")
+                };
+
+                if self.colour && !colour.is_empty() {
+                    write!(f, "{}{}{}", colour, c, RESET)?;
+                } else {
+                    write!(f, "{}", c)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
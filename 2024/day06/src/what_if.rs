@@ -0,0 +1,92 @@
+use simulate::{simulate, Outcome};
+
+use crate::{GuardDirection, Guards, Map, MapCell};
+
+/// An obstacle that traps the guard, along with the walk that proves it.
+#[derive(Debug, Clone)]
+pub struct LoopObstacle {
+    pub obstacle: (usize, usize),
+
+    /// Every step the guards take from the start until they repeat themselves.
+    pub path: Vec<Guards>,
+
+    /// Index into `path` where the loop begins.
+    pub cycle_start: usize,
+}
+
+impl LoopObstacle {
+    /// The cells and directions that the guards repeat forever.
+    pub fn cycle(&self) -> &[Guards] {
+        &self.path[self.cycle_start..]
+    }
+}
+
+impl Map {
+    /// Every obstacle position that makes the guard loop, each with the loop
+    /// it causes, in the order the guard would first reach them.
+    pub fn what_if(&self, jobs: usize) -> Vec<LoopObstacle> {
+        self.loop_obstacles(jobs)
+            .into_iter()
+            .map(|obstacle| {
+                let simulation = simulate(self.guards.clone(), |guards| {
+                    self.step_all(guards, Some(obstacle))
+                });
+                let Outcome::Loop { start, .. } = simulation.outcome else {
+                    panic!("Obstacle at {:?} was expected to cause a loop", obstacle);
+                };
+
+                LoopObstacle {
+                    obstacle,
+                    path: simulation.trace,
+                    cycle_start: start,
+                }
+            })
+            .collect()
+    }
+
+    /// Draws the map the way the puzzle text illustrates a loop: the added
+    /// obstacle as `O`, the guard's walk as `|` and `-`, with `+` wherever
+    /// they turn or cross their own path, and the guards at their start.
+    pub fn render_what_if(&self, what_if: &LoopObstacle) -> String {
+        let mut vertical = vec![false; self.width * self.height];
+        let mut horizontal = vec![false; self.width * self.height];
+
+        for &(x, y, direction) in what_if.path.iter().flatten() {
+            match direction {
+                GuardDirection::Up | GuardDirection::Down => vertical[y * self.width + x] = true,
+                GuardDirection::Left | GuardDirection::Right => {
+                    horizontal[y * self.width + x] = true
+                }
+            }
+        }
+
+        let mut output = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let guard = self
+                    .guards
+                    .iter()
+                    .find(|guard| (guard.0, guard.1) == (x, y));
+                let c = if let Some(guard) = guard {
+                    guard.2.glyph()
+                } else if (x, y) == what_if.obstacle {
+                    'O'
+                } else if self.data[index] == MapCell::Obstacle {
+                    '#'
+                } else {
+                    match (vertical[index], horizontal[index]) {
+                        (true, true) => '+',
+                        (true, false) => '|',
+                        (false, true) => '-',
+                        (false, false) => '.',
+                    }
+                };
+                output.push(c);
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+}
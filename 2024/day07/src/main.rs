@@ -1,95 +1,231 @@
+use std::fmt::Display;
+
+use expression::{evaluate, Evaluation, Number, Operation, Operator};
 use iterbox::CombinationIterator;
 use meaningful_lines::MeaningfulLines;
+use parallel::parallel_map;
 
 fn main() {
-    part1();
-    part2();
+    let options = Options::from_args();
+
+    // Some inputs have targets that don't fit in 64 bits, so allow the wider
+    // type to be opted into.
+    if options.wide {
+        run::<u128>(&options);
+    } else {
+        run::<u64>(&options);
+    }
+}
+
+fn run<N: Number + Send + Sync>(options: &Options) {
+    match &options.operators {
+        Some(operators) => variant::<N>(operators, options),
+        None => {
+            part1::<N>(options);
+            part2::<N>(options);
+        }
+    }
 }
 
-fn part1() {
-    let lines = MeaningfulLines::new(include_str!("../data.txt"));
+fn part1<N: Number + Send + Sync>(options: &Options) {
     let operators = vec![Operator::Add, Operator::Multiply];
 
-    let result = lines
-        .map(parse_line)
-        .filter_map(|(result, elems)| valid_calculation(result, &elems, &operators))
-        .sum::<u64>();
+    let result = solve::<N, _>(include_str!("../data.txt"), &operators, options);
 
     println!("Part 1: {}", result);
 }
 
-fn part2() {
-    let lines = MeaningfulLines::new(include_str!("../data.txt"));
-    let operators = vec![Operator2::Add, Operator2::Multiply, Operator2::Concatenate];
+fn part2<N: Number + Send + Sync>(options: &Options) {
+    let operators = vec![Operator::Add, Operator::Multiply, Operator::Concatenate];
 
-    let result = lines
-        .map(parse_line)
-        .filter_map(|(result, elems)| valid_calculation(result, &elems, &operators))
-        .sum::<u64>();
+    let result = solve::<N, _>(include_str!("../data.txt"), &operators, options);
 
     println!("Part 2: {}", result);
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Operator {
-    Add,
-    Multiply,
+// Solves the puzzle with an operator set chosen on the command line.
+fn variant<N: Number + Send + Sync>(operators: &[Operator], options: &Options) {
+    let result = solve::<N, _>(include_str!("../data.txt"), operators, options);
+
+    let names = operators
+        .iter()
+        .map(|operator| operator.name())
+        .collect::<Vec<_>>()
+        .join(",");
+    println!("Variant ({}, {:?}): {}", names, options.evaluation, result);
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Operator2 {
-    Add,
-    Multiply,
-    Concatenate,
+// Checks every equation, spread over `options.jobs` threads, and returns the
+// sum of the targets that can be made.  Reports are printed in input order
+// once all the checks are done.
+fn solve<N, T>(input: &str, operators: &[T], options: &Options) -> N
+where
+    N: Number + Send + Sync,
+    T: Copy + Operation + Display + Send + Sync,
+{
+    let equations = MeaningfulLines::new(input)
+        .map(parse_line::<N>)
+        .collect::<Vec<_>>();
+
+    let solutions = parallel_map(&equations, options.jobs, |(result, elems)| {
+        if options.all {
+            all_valid_calculations(*result, elems, operators, options.evaluation)
+        } else {
+            valid_calculation(*result, elems, operators, options.evaluation)
+                .into_iter()
+                .collect()
+        }
+    });
+
+    solutions
+        .iter()
+        .filter_map(|solutions| report(solutions, options))
+        .sum()
 }
 
-trait Operation {
-    fn apply(&self, a: u64, b: u64) -> u64;
+#[derive(Debug, Default)]
+struct Options {
+    // Evaluate in u128 instead of u64 (--u128).
+    wide: bool,
+
+    // Print the operator sequence that solves each equation (--witness).
+    witness: bool,
+
+    // Enumerate every satisfying operator sequence, not just the first (--all).
+    all: bool,
+
+    // Replace both parts with a single run using these operators
+    // (--ops add,mul,cat or --ops +,*,||).
+    operators: Option<Vec<Operator>>,
+
+    // How an operator sequence is evaluated (--precedence).
+    evaluation: Evaluation,
+
+    // Number of threads to check equations on (--jobs N or --jobs=N, 0 for
+    // all cores).
+    jobs: usize,
 }
 
-impl Operation for Operator {
-    fn apply(&self, a: u64, b: u64) -> u64 {
-        match self {
-            Operator::Add => a + b,
-            Operator::Multiply => a * b,
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Options {
+            jobs: parallel::jobs_from_args(),
+            ..Options::default()
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--u128" => options.wide = true,
+                "--witness" => options.witness = true,
+                "--all" => options.all = true,
+                "--precedence" => options.evaluation = Evaluation::Precedence,
+                "--left-to-right" => options.evaluation = Evaluation::LeftToRight,
+                // Already read by `jobs_from_args`.
+                "--jobs" => {
+                    args.next();
+                }
+                _ if arg.starts_with("--jobs=") => {}
+                "--ops" => {
+                    let list = args.next().expect("--ops needs a list of operators");
+                    options.operators = Some(parse_operators(&list));
+                }
+                _ => panic!("Unknown argument: {}", arg),
+            }
         }
+
+        options
     }
 }
 
-impl Operation for Operator2 {
-    fn apply(&self, a: u64, b: u64) -> u64 {
-        match self {
-            Operator2::Add => a + b,
-            Operator2::Multiply => a * b,
-            Operator2::Concatenate => {
-                let a_string = a.to_string();
-                let b_string = b.to_string();
-
-                let concatenated_string = format!("{}{}", a_string, b_string);
+// Looks up a comma separated list of operator names or symbols.
+fn parse_operators(list: &str) -> Vec<Operator> {
+    list.split(',')
+        .map(|name| {
+            Operator::from_name(name).unwrap_or_else(|| panic!("Unknown operator: {}", name))
+        })
+        .collect()
+}
 
-                concatenated_string.parse().unwrap()
-            }
+// Prints the solutions found for a single equation as requested by the
+// options.  Returns the target if the equation can be solved.
+fn report<N, T>(solutions: &[Solution<N, T>], options: &Options) -> Option<N>
+where
+    N: Number,
+    T: Display,
+{
+    if options.witness {
+        for solution in solutions {
+            println!("{}", solution);
         }
     }
+
+    let first = solutions.first()?;
+    if options.all {
+        println!(
+            "{}: {} satisfying sequence(s)",
+            first.result,
+            solutions.len()
+        );
+    }
+
+    Some(first.result)
 }
 
-fn parse_line(line: &str) -> (u64, Vec<u64>) {
+fn parse_line<N: Number>(line: &str) -> (N, Vec<N>) {
     let mut parts = line.split(':');
 
-    let result = parts.next().unwrap().parse().unwrap();
+    let result = parts
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|_| panic!("Target doesn't fit in the number type: {}", line));
 
     let elems = parts
         .next()
         .unwrap()
         .split_whitespace()
-        .map(|x| x.parse().unwrap())
+        .map(|x| {
+            x.parse()
+                .unwrap_or_else(|_| panic!("Operand doesn't fit in the number type: {}", x))
+        })
         .collect();
 
     (result, elems)
 }
 
-fn valid_calculation<T>(result: u64, elems: &[u64], operators: &[T]) -> Option<u64>
+/// A witness for a solvable equation: the operator sequence that turns the
+/// operands into the target under the evaluation rules used to find it.
+#[derive(Debug, Clone, PartialEq)]
+struct Solution<N, T> {
+    result: N,
+    elems: Vec<N>,
+    operators: Vec<T>,
+}
+
+impl<N, T> Display for Solution<N, T>
+where
+    N: Display,
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.result, self.elems[0])?;
+        for (operator, elem) in self.operators.iter().zip(&self.elems[1..]) {
+            write!(f, " {} {}", operator, elem)?;
+        }
+        Ok(())
+    }
+}
+
+// Lazily yields every operator sequence that solves the equation.
+fn solutions<'a, N, T>(
+    result: N,
+    elems: &'a [N],
+    operators: &'a [T],
+    evaluation: Evaluation,
+) -> impl Iterator<Item = Solution<N, T>> + 'a
 where
+    N: Number + 'a,
     T: Copy + Operation,
 {
     // Create a vector of all the permutations of the operators
@@ -97,17 +233,154 @@ where
 
     let combinations = CombinationIterator::new(operators, n);
 
-    for combination in combinations {
-        let mut partial_result = elems[0];
+    combinations.filter_map(move |combination| {
+        // An overflow anywhere in the chain means this combination can't match.
+        let partial_result = evaluate(elems, &combination, evaluation);
 
-        for (i, operator) in combination.iter().enumerate() {
-            partial_result = operator.apply(partial_result, elems[i + 1]);
-        }
+        (partial_result == Some(result)).then(|| Solution {
+            result,
+            elems: elems.to_vec(),
+            operators: combination,
+        })
+    })
+}
 
-        if partial_result == result {
-            return Some(result);
-        }
+fn valid_calculation<N, T>(
+    result: N,
+    elems: &[N],
+    operators: &[T],
+    evaluation: Evaluation,
+) -> Option<Solution<N, T>>
+where
+    N: Number,
+    T: Copy + Operation,
+{
+    solutions(result, elems, operators, evaluation).next()
+}
+
+fn all_valid_calculations<N, T>(
+    result: N,
+    elems: &[N],
+    operators: &[T],
+    evaluation: Evaluation,
+) -> Vec<Solution<N, T>>
+where
+    N: Number,
+    T: Copy + Operation,
+{
+    solutions(result, elems, operators, evaluation).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use expression::Equation;
+
+    use super::*;
+
+    fn example() -> Vec<(u64, Vec<u64>)> {
+        MeaningfulLines::new(include_str!("../test.txt"))
+            .map(parse_line::<u64>)
+            .collect()
     }
 
-    None
+    #[test]
+    fn test_witness() {
+        let operators = [Operator::Add, Operator::Multiply];
+
+        let solution = valid_calculation(190_u64, &[10, 19], &operators, Evaluation::LeftToRight);
+        assert_eq!(solution.unwrap().to_string(), "190 = 10 * 19");
+
+        let solution = valid_calculation(
+            292_u64,
+            &[11, 6, 16, 20],
+            &operators,
+            Evaluation::LeftToRight,
+        );
+        assert_eq!(solution.unwrap().to_string(), "292 = 11 + 6 * 16 + 20");
+
+        let solution = valid_calculation(83_u64, &[17, 5], &operators, Evaluation::LeftToRight);
+        assert_eq!(solution, None);
+    }
+
+    #[test]
+    fn test_all_valid_calculations() {
+        let operators = [Operator::Add, Operator::Multiply];
+        let counts = example()
+            .iter()
+            .map(|(result, elems)| {
+                all_valid_calculations(*result, elems, &operators, Evaluation::LeftToRight).len()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 2, 0, 0, 0, 0, 0, 0, 1]);
+
+        let witnesses =
+            all_valid_calculations(3267_u64, &[81, 40, 27], &operators, Evaluation::LeftToRight)
+                .iter()
+                .map(|solution| solution.to_string())
+                .collect::<Vec<_>>();
+        assert_eq!(witnesses.len(), 2);
+        assert!(witnesses.contains(&"3267 = 81 + 40 * 27".to_string()));
+        assert!(witnesses.contains(&"3267 = 81 * 40 + 27".to_string()));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let operators = [Operator::Add, Operator::Multiply];
+
+        // 2 + 3 * 4 is 20 left to right, but 14 with precedence.
+        assert_eq!(
+            valid_calculation(14_u64, &[2, 3, 4], &operators, Evaluation::LeftToRight),
+            None
+        );
+        let solution = valid_calculation(14_u64, &[2, 3, 4], &operators, Evaluation::Precedence);
+        assert_eq!(solution.unwrap().to_string(), "14 = 2 + 3 * 4");
+        let solution = valid_calculation(20_u64, &[2, 3, 4], &operators, Evaluation::LeftToRight);
+        assert_eq!(solution.unwrap().to_string(), "20 = 2 + 3 * 4");
+    }
+
+    #[test]
+    fn test_registry_operators() {
+        let operators = parse_operators("sub,**");
+        assert_eq!(operators, vec![Operator::Subtract, Operator::Power]);
+
+        // 10 - 2 ** 3 is 512 left to right, but 2 with precedence.
+        let elems = [10_u64, 2, 3];
+        let solution = valid_calculation(512, &elems, &operators, Evaluation::LeftToRight);
+        assert_eq!(solution.unwrap().to_string(), "512 = 10 - 2 ** 3");
+        let solution = valid_calculation(2, &elems, &operators, Evaluation::Precedence);
+        assert_eq!(solution.unwrap().to_string(), "2 = 10 - 2 ** 3");
+        assert_eq!(
+            valid_calculation(2, &elems, &operators, Evaluation::LeftToRight),
+            None
+        );
+
+        // Exponents are right associative: 2 ** 3 ** 2 is 2 ** 9.
+        let elems = [2_u64, 3, 2];
+        let solution = valid_calculation(512, &elems, &operators, Evaluation::Precedence);
+        assert_eq!(solution.unwrap().to_string(), "512 = 2 ** 3 ** 2");
+        let solution = valid_calculation(64, &elems, &operators, Evaluation::LeftToRight);
+        assert_eq!(solution.unwrap().to_string(), "64 = 2 ** 3 ** 2");
+    }
+
+    // Every witness printed for the example should parse back into an
+    // equation that holds under the same evaluation rules.
+    #[test]
+    fn test_witness_round_trip() {
+        let operators = [Operator::Add, Operator::Multiply, Operator::Concatenate];
+
+        for evaluation in [Evaluation::LeftToRight, Evaluation::Precedence] {
+            for (result, elems) in
+                MeaningfulLines::new(include_str!("../test.txt")).map(parse_line::<u64>)
+            {
+                for solution in all_valid_calculations(result, &elems, &operators, evaluation) {
+                    let equation = Equation::<u64>::parse(&solution.to_string()).unwrap();
+
+                    assert_eq!(equation.target, result);
+                    assert_eq!(equation.expression.operands, elems);
+                    assert_eq!(equation.expression.operators, solution.operators);
+                    assert!(equation.holds(evaluation));
+                }
+            }
+        }
+    }
 }
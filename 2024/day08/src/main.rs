@@ -1,8 +1,10 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
+    path::{Path, PathBuf},
 };
 
+use grid_image::{Canvas, Rgb};
 use meaningful_lines::MeaningfulLines;
 
 fn main() {
@@ -14,12 +16,39 @@ fn part1() {
     let world = World::new(include_str!("../data.txt"), process_antinodes);
 
     println!("Solution to part1: {}", world.number_antinodes());
+
+    if let Some(directory) = image_directory() {
+        world
+            .save_image(directory.join("part1.png"))
+            .expect("Unable to write image");
+    }
 }
 
 fn part2() {
     let world = World::new(include_str!("../data.txt"), process_antinodes_2);
 
     println!("Solution to part2: {}", world.number_antinodes());
+
+    if let Some(directory) = image_directory() {
+        world
+            .save_image(directory.join("part2.png"))
+            .expect("Unable to write image");
+    }
+}
+
+// The directory to write images of the antinodes to (--image DIR).
+fn image_directory() -> Option<PathBuf> {
+    arg_value("--image").map(PathBuf::from)
+}
+
+// Returns the value following a command line flag, e.g. `--image out`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    Some(
+        args.next()
+            .unwrap_or_else(|| panic!("{} needs a value", name)),
+    )
 }
 
 struct World {
@@ -88,6 +117,18 @@ impl World {
             // And return the number of antinodes
             .len()
     }
+
+    // Draws the antennas, each frequency in its own colour, with the
+    // antinodes marked on top so that antennas underneath still show.
+    fn save_image(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut canvas = Canvas::new(self.width, self.height, 6);
+        canvas.fill(|x, y| match self.map[y * self.width + x] {
+            '.' => Rgb::BLACK,
+            c => Rgb::from_key(c as u32),
+        });
+        canvas.mark(self.antinodes.values().flatten().copied(), Rgb::RED);
+        canvas.save(path)
+    }
 }
 
 impl Display for World {
@@ -0,0 +1,179 @@
+use std::fmt::{self, Display, Write};
+
+/// A directed graph that can be written out in Graphviz's DOT language, e.g.
+/// with `dot -Tsvg graph.dot > graph.svg`.  Nodes and edges appear in the
+/// order they were added, and any attributes are passed through untouched,
+/// so the caller decides how things should look.
+#[derive(Debug, Clone, Default)]
+pub struct Graph {
+    name: String,
+    attributes: Attributes,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    id: String,
+    attributes: Attributes,
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    from: String,
+    to: String,
+    attributes: Attributes,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Attributes(Vec<(String, String)>);
+
+impl Graph {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets an attribute of the whole graph, e.g. `rankdir=LR`.
+    pub fn attr(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.attributes.set(key, value);
+        self
+    }
+
+    /// Adds a node, or returns the existing one with the same id so that
+    /// attributes can be added to it.
+    pub fn node(&mut self, id: impl Display) -> &mut Node {
+        let id = id.to_string();
+        let index = match self.nodes.iter().position(|node| node.id == id) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(Node {
+                    id,
+                    attributes: Attributes::default(),
+                });
+                self.nodes.len() - 1
+            }
+        };
+        &mut self.nodes[index]
+    }
+
+    /// Adds an edge between two nodes.  The nodes don't need to be added
+    /// first, unless they need attributes of their own.
+    pub fn edge(&mut self, from: impl Display, to: impl Display) -> &mut Edge {
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            attributes: Attributes::default(),
+        });
+        self.edges.last_mut().unwrap()
+    }
+}
+
+impl Node {
+    pub fn attr(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.attributes.set(key, value);
+        self
+    }
+}
+
+impl Edge {
+    pub fn attr(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.attributes.set(key, value);
+        self
+    }
+}
+
+impl Attributes {
+    fn set(&mut self, key: &str, value: impl Display) {
+        let value = value.to_string();
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.0.push((key.to_string(), value)),
+        }
+    }
+}
+
+impl Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        let attributes = self
+            .0
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, quote(value)))
+            .collect::<Vec<_>>();
+        write!(f, " [{}]", attributes.join(", "))
+    }
+}
+
+impl Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "digraph {} {{", quote(&self.name))?;
+        for (key, value) in &self.attributes.0 {
+            writeln!(f, "    {}={};", key, quote(value))?;
+        }
+        for node in &self.nodes {
+            writeln!(f, "    {}{};", quote(&node.id), node.attributes)?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                f,
+                "    {} -> {}{};",
+                quote(&edge.from),
+                quote(&edge.to),
+                edge.attributes
+            )?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+// Every id and value is quoted, which DOT always accepts, so that page
+// numbers, names with spaces etc. need no special treatment.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.write_char(c).unwrap(),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph() {
+        let mut graph = Graph::new("rules");
+        graph.attr("rankdir", "LR");
+        graph.node(47).attr("shape", "box");
+        graph.edge(47, 53);
+        graph.edge(97, 47).attr("color", "red").attr("penwidth", 2);
+        graph.node(47).attr("shape", "circle");
+
+        assert_eq!(
+            graph.to_string(),
+            "digraph \"rules\" {\n    \
+                rankdir=\"LR\";\n    \
+                \"47\" [shape=\"circle\"];\n    \
+                \"47\" -> \"53\";\n    \
+                \"97\" -> \"47\" [color=\"red\", penwidth=\"2\"];\n\
+            }\n"
+        );
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(quote("a \"b\"\\c\nd"), "\"a \\\"b\\\"\\\\c\\nd\"");
+    }
+}
@@ -0,0 +1,35 @@
+use std::io::BufRead;
+
+use expression::{Equation, Evaluation};
+
+// Reads equations such as `292 = 11 + 6 * 16 + 20` from stdin, one per line,
+// and reports whether each one holds.
+fn main() {
+    let evaluation = if std::env::args().any(|arg| arg == "--precedence") {
+        Evaluation::Precedence
+    } else {
+        Evaluation::LeftToRight
+    };
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.expect("Unable to read stdin");
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Equation::<u128>::parse(&line) {
+            Ok(equation) => {
+                let value = equation.expression.evaluate(evaluation);
+                match value {
+                    Some(value) if value == equation.target => println!("OK    {}", equation),
+                    Some(value) => println!("FAIL  {} (evaluates to {})", equation, value),
+                    None => println!("FAIL  {} (overflows)", equation),
+                }
+            }
+            Err(error) => {
+                println!("ERROR {}", line);
+                println!("      {}^ {}", " ".repeat(error.position), error.kind);
+            }
+        }
+    }
+}
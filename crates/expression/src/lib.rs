@@ -0,0 +1,547 @@
+use std::{fmt::Display, iter::Sum, str::FromStr};
+
+/// The integer types that expressions can be evaluated in.  All arithmetic is
+/// checked so that an overflowing operator chain is rejected rather than
+/// wrapping (release) or panicking (debug).  The types are unsigned, so a
+/// subtraction that would go negative is rejected the same way.
+pub trait Number: Copy + PartialEq + FromStr + Sum + Display {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+
+    /// Integer division, rounding towards zero.  Division by zero is `None`.
+    fn checked_div(self, other: Self) -> Option<Self>;
+
+    fn checked_exp(self, other: Self) -> Option<Self>;
+    fn xor(self, other: Self) -> Self;
+
+    /// Appends the decimal digits of `other` to `self`, e.g. 12 || 345 = 12345.
+    fn checked_concat(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+
+                fn checked_div(self, other: Self) -> Option<Self> {
+                    <$t>::checked_div(self, other)
+                }
+
+                fn checked_exp(self, other: Self) -> Option<Self> {
+                    self.checked_pow(u32::try_from(other).ok()?)
+                }
+
+                fn xor(self, other: Self) -> Self {
+                    self ^ other
+                }
+
+                fn checked_concat(self, other: Self) -> Option<Self> {
+                    // Shift `self` left by as many decimal digits as `other` has.
+                    let digits = other.checked_ilog10().unwrap_or(0) + 1;
+                    let shift = <$t>::checked_pow(10, digits)?;
+
+                    self.checked_mul(shift)?.checked_add(other)
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(u64, u128);
+
+/// Every operator an expression can contain.  Which ones are in play for a
+/// puzzle is decided by the caller, so a variant only needs a new entry here.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Xor,
+    Concatenate,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 7] = [
+        Operator::Add,
+        Operator::Subtract,
+        Operator::Multiply,
+        Operator::Divide,
+        Operator::Power,
+        Operator::Xor,
+        Operator::Concatenate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operator::Add => "add",
+            Operator::Subtract => "sub",
+            Operator::Multiply => "mul",
+            Operator::Divide => "div",
+            Operator::Power => "pow",
+            Operator::Xor => "xor",
+            Operator::Concatenate => "cat",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Subtract => "-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Power => "**",
+            Operator::Xor => "^",
+            Operator::Concatenate => "||",
+        }
+    }
+
+    /// Looks an operator up by either its name or its symbol.
+    pub fn from_name(name: &str) -> Option<Operator> {
+        Operator::ALL
+            .into_iter()
+            .find(|operator| operator.name() == name || operator.symbol() == name)
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+pub trait Operation {
+    /// Applies the operator, returning `None` if the result overflows.
+    fn apply<N: Number>(&self, a: N, b: N) -> Option<N>;
+
+    /// Binding strength when evaluating with precedence; higher binds tighter.
+    fn precedence(&self) -> u8;
+
+    fn right_associative(&self) -> bool {
+        false
+    }
+}
+
+impl Operation for Operator {
+    fn apply<N: Number>(&self, a: N, b: N) -> Option<N> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Subtract => a.checked_sub(b),
+            Operator::Multiply => a.checked_mul(b),
+            Operator::Divide => a.checked_div(b),
+            Operator::Power => a.checked_exp(b),
+            Operator::Xor => Some(a.xor(b)),
+            Operator::Concatenate => a.checked_concat(b),
+        }
+    }
+
+    // Follows the usual conventions: exponent binds tightest, then the
+    // multiplicative and additive operators.  Concatenation and XOR sit below
+    // the arithmetic ones, as bitwise and string operators do in most languages.
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Power => 4,
+            Operator::Multiply | Operator::Divide => 3,
+            Operator::Add | Operator::Subtract => 2,
+            Operator::Concatenate => 1,
+            Operator::Xor => 0,
+        }
+    }
+
+    fn right_associative(&self) -> bool {
+        matches!(self, Operator::Power)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Evaluation {
+    /// Apply operators strictly in the order they appear.
+    #[default]
+    LeftToRight,
+
+    /// Apply operators according to their precedence and associativity.
+    Precedence,
+}
+
+/// Evaluates `elems[0] op[0] elems[1] op[1] ...`, returning `None` if any step
+/// overflows.  There must be exactly one more operand than operators.
+pub fn evaluate<N, T>(elems: &[N], operators: &[T], evaluation: Evaluation) -> Option<N>
+where
+    N: Number,
+    T: Copy + Operation,
+{
+    match evaluation {
+        Evaluation::LeftToRight => operators
+            .iter()
+            .zip(&elems[1..])
+            .try_fold(elems[0], |partial_result, (operator, &elem)| {
+                operator.apply(partial_result, elem)
+            }),
+        Evaluation::Precedence => {
+            // Operator-precedence parsing over the already tokenised expression.
+            let mut values = vec![elems[0]];
+            let mut pending: Vec<T> = Vec::new();
+
+            for (&operator, &elem) in operators.iter().zip(&elems[1..]) {
+                while let Some(&top) = pending.last() {
+                    let binds_tighter = top.precedence() > operator.precedence()
+                        || (top.precedence() == operator.precedence()
+                            && !operator.right_associative());
+                    if !binds_tighter {
+                        break;
+                    }
+                    pending.pop();
+                    reduce(&mut values, top)?;
+                }
+                pending.push(operator);
+                values.push(elem);
+            }
+
+            while let Some(top) = pending.pop() {
+                reduce(&mut values, top)?;
+            }
+
+            values.pop()
+        }
+    }
+}
+
+// Replaces the top two values with the result of applying the operator to them.
+fn reduce<N: Number, T: Operation>(values: &mut Vec<N>, operator: T) -> Option<()> {
+    let b = values.pop()?;
+    let a = values.pop()?;
+    values.push(operator.apply(a, b)?);
+    Some(())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Token<N> {
+    Number(N),
+    Operator(Operator),
+    Equals,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedCharacter(char),
+    NumberTooLarge,
+    ExpectedNumber,
+    ExpectedOperator,
+    ExpectedEquals,
+    UnexpectedEquals,
+}
+
+/// A parse error, with the byte offset into the input where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub position: usize,
+    pub kind: ErrorKind,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            ErrorKind::NumberTooLarge => write!(f, "number too large"),
+            ErrorKind::ExpectedNumber => write!(f, "expected a number"),
+            ErrorKind::ExpectedOperator => write!(f, "expected an operator"),
+            ErrorKind::ExpectedEquals => write!(f, "expected '='"),
+            ErrorKind::UnexpectedEquals => write!(f, "unexpected '='"),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Splits the input into tokens, each paired with its byte offset.
+pub fn tokenize<N: Number>(input: &str) -> Result<Vec<(usize, Token<N>)>, Error> {
+    // Longer symbols first so that `**` isn't read as two `*`.
+    let mut symbols = Operator::ALL;
+    symbols.sort_by_key(|operator| std::cmp::Reverse(operator.symbol().len()));
+
+    let mut tokens = Vec::new();
+    let mut cursor = 0;
+
+    while let Some(c) = input[cursor..].chars().next() {
+        let rest = &input[cursor..];
+
+        if c.is_whitespace() {
+            cursor += c.len_utf8();
+        } else if c.is_ascii_digit() {
+            let num_digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+            let number = rest[..num_digits].parse().map_err(|_| Error {
+                position: cursor,
+                kind: ErrorKind::NumberTooLarge,
+            })?;
+            tokens.push((cursor, Token::Number(number)));
+            cursor += num_digits;
+        } else if c == '=' {
+            tokens.push((cursor, Token::Equals));
+            cursor += 1;
+        } else if let Some(operator) = symbols.iter().find(|op| rest.starts_with(op.symbol())) {
+            tokens.push((cursor, Token::Operator(*operator)));
+            cursor += operator.symbol().len();
+        } else {
+            return Err(Error {
+                position: cursor,
+                kind: ErrorKind::UnexpectedCharacter(c),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A flat infix expression: `operands[0] operators[0] operands[1] ...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression<N> {
+    pub operands: Vec<N>,
+    pub operators: Vec<Operator>,
+}
+
+impl<N: Number> Expression<N> {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let tokens = tokenize(input)?;
+        Self::from_tokens(&tokens, input.len())
+    }
+
+    // Parses `number (operator number)*`.  `end` is the position reported
+    // when the tokens run out.
+    fn from_tokens(tokens: &[(usize, Token<N>)], end: usize) -> Result<Self, Error> {
+        let mut operands = Vec::new();
+        let mut operators = Vec::new();
+        let mut tokens = tokens.iter();
+
+        loop {
+            match tokens.next() {
+                Some(&(_, Token::Number(number))) => operands.push(number),
+                Some(&(position, _)) => {
+                    return Err(Error {
+                        position,
+                        kind: ErrorKind::ExpectedNumber,
+                    })
+                }
+                None => {
+                    return Err(Error {
+                        position: end,
+                        kind: ErrorKind::ExpectedNumber,
+                    })
+                }
+            }
+
+            match tokens.next() {
+                Some(&(_, Token::Operator(operator))) => operators.push(operator),
+                Some(&(position, Token::Equals)) => {
+                    return Err(Error {
+                        position,
+                        kind: ErrorKind::UnexpectedEquals,
+                    })
+                }
+                Some(&(position, Token::Number(_))) => {
+                    return Err(Error {
+                        position,
+                        kind: ErrorKind::ExpectedOperator,
+                    })
+                }
+                None => break,
+            }
+        }
+
+        Ok(Expression {
+            operands,
+            operators,
+        })
+    }
+
+    pub fn evaluate(&self, evaluation: Evaluation) -> Option<N> {
+        evaluate(&self.operands, &self.operators, evaluation)
+    }
+}
+
+impl<N: Display> Display for Expression<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.operands[0])?;
+        for (operator, operand) in self.operators.iter().zip(&self.operands[1..]) {
+            write!(f, " {} {}", operator, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// An equation of the form `target = expression`, e.g. `292 = 11 + 6 * 16 + 20`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equation<N> {
+    pub target: N,
+    pub expression: Expression<N>,
+}
+
+impl<N: Number> Equation<N> {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let tokens = tokenize(input)?;
+
+        let target = match tokens.first() {
+            Some(&(_, Token::Number(number))) => number,
+            Some(&(position, _)) => {
+                return Err(Error {
+                    position,
+                    kind: ErrorKind::ExpectedNumber,
+                })
+            }
+            None => {
+                return Err(Error {
+                    position: input.len(),
+                    kind: ErrorKind::ExpectedNumber,
+                })
+            }
+        };
+
+        match tokens.get(1) {
+            Some(&(_, Token::Equals)) => {}
+            Some(&(position, _)) => {
+                return Err(Error {
+                    position,
+                    kind: ErrorKind::ExpectedEquals,
+                })
+            }
+            None => {
+                return Err(Error {
+                    position: input.len(),
+                    kind: ErrorKind::ExpectedEquals,
+                })
+            }
+        }
+
+        let expression = Expression::from_tokens(&tokens[2..], input.len())?;
+
+        Ok(Equation { target, expression })
+    }
+
+    /// Returns whether the expression evaluates to the target.  An overflowing
+    /// expression never holds.
+    pub fn holds(&self, evaluation: Evaluation) -> bool {
+        self.expression.evaluate(evaluation) == Some(self.target)
+    }
+}
+
+impl<N: Display> Display for Equation<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.target, self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluation_modes() {
+        let expression = Expression::<u64>::parse("11 + 6 * 16 + 20").unwrap();
+
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), Some(292));
+        assert_eq!(expression.evaluate(Evaluation::Precedence), Some(127));
+
+        let expression = Expression::<u64>::parse("2 + 3 * 4").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), Some(20));
+        assert_eq!(expression.evaluate(Evaluation::Precedence), Some(14));
+
+        // Concatenation binds looser than the arithmetic operators.
+        let expression = Expression::<u64>::parse("1 || 2 * 3").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), Some(36));
+        assert_eq!(expression.evaluate(Evaluation::Precedence), Some(16));
+    }
+
+    #[test]
+    fn test_concatenation_and_power() {
+        let expression = Expression::<u64>::parse("6 * 8 || 6 * 15").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), Some(7290));
+
+        let expression = Expression::<u64>::parse("2 ** 3 ** 2").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), Some(64));
+        assert_eq!(expression.evaluate(Evaluation::Precedence), Some(512));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let expression = Expression::<u64>::parse("18446744073709551615 + 1").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), None);
+
+        let expression = Expression::<u64>::parse("1 - 2").unwrap();
+        assert_eq!(expression.evaluate(Evaluation::LeftToRight), None);
+    }
+
+    #[test]
+    fn test_equation() {
+        let equation = Equation::<u64>::parse("3267 = 81 + 40 * 27").unwrap();
+
+        assert!(equation.holds(Evaluation::LeftToRight));
+        assert!(!equation.holds(Evaluation::Precedence));
+        assert_eq!(equation.to_string(), "3267 = 81 + 40 * 27");
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let error = |input| Equation::<u64>::parse(input).unwrap_err();
+
+        assert_eq!(
+            error("292 = 11 + 6 % 16"),
+            Error {
+                position: 13,
+                kind: ErrorKind::UnexpectedCharacter('%')
+            }
+        );
+        assert_eq!(
+            error("292 = 11 + * 16"),
+            Error {
+                position: 11,
+                kind: ErrorKind::ExpectedNumber
+            }
+        );
+        assert_eq!(
+            error("292 = 11 6"),
+            Error {
+                position: 9,
+                kind: ErrorKind::ExpectedOperator
+            }
+        );
+        assert_eq!(
+            error("292 = 11 +"),
+            Error {
+                position: 10,
+                kind: ErrorKind::ExpectedNumber
+            }
+        );
+        assert_eq!(
+            error("292 11"),
+            Error {
+                position: 4,
+                kind: ErrorKind::ExpectedEquals
+            }
+        );
+        assert_eq!(
+            error("99999999999999999999 = 1"),
+            Error {
+                position: 0,
+                kind: ErrorKind::NumberTooLarge
+            }
+        );
+    }
+}
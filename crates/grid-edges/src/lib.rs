@@ -0,0 +1,105 @@
+/// How a grid treats positions beyond its edges, so that the same puzzle code
+/// can run on a bounded grid or a wrapped-around one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Edges {
+    /// There's nothing beyond the edges: stepping off the grid fails.
+    #[default]
+    Bounded,
+
+    /// The grid is a torus.  Stepping off one edge comes back on at the
+    /// opposite one, so every step succeeds.
+    Wrap,
+}
+
+/// The 8 directions to the cells surrounding a cell, clockwise from up.
+pub const NEIGHBOURS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Just the 4 directions along the rows and columns, clockwise from up.
+pub const ORTHOGONAL_NEIGHBOURS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+impl Edges {
+    /// The cell `distance` steps of `delta` away from `from` on a grid of the
+    /// given size (width, height), or `None` if that's off a bounded grid.
+    pub fn offset(
+        self,
+        from: (usize, usize),
+        delta: (isize, isize),
+        distance: isize,
+        size: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let (width, height) = (size.0 as isize, size.1 as isize);
+        let x = from.0 as isize + delta.0 * distance;
+        let y = from.1 as isize + delta.1 * distance;
+
+        match self {
+            Edges::Bounded => ((0..width).contains(&x) && (0..height).contains(&y))
+                .then_some((x as usize, y as usize)),
+            Edges::Wrap => Some((x.rem_euclid(width) as usize, y.rem_euclid(height) as usize)),
+        }
+    }
+
+    /// The next cell in the direction of `delta`.
+    pub fn step(
+        self,
+        from: (usize, usize),
+        delta: (isize, isize),
+        size: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        self.offset(from, delta, 1, size)
+    }
+
+    /// The cells one step away in each of the directions given (usually
+    /// `NEIGHBOURS` or `ORTHOGONAL_NEIGHBOURS`).  On a wrapped grid less than
+    /// 3 cells across, the same cell can be more than one neighbour, or even
+    /// the cell itself.
+    pub fn neighbours<'a>(
+        self,
+        from: (usize, usize),
+        deltas: &'a [(isize, isize)],
+        size: (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        deltas
+            .iter()
+            .filter_map(move |&delta| self.step(from, delta, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step() {
+        let size = (4, 3);
+        assert_eq!(Edges::Bounded.step((3, 1), (1, 0), size), None);
+        assert_eq!(Edges::Wrap.step((3, 1), (1, 0), size), Some((0, 1)));
+        assert_eq!(Edges::Wrap.step((0, 0), (-1, -1), size), Some((3, 2)));
+        assert_eq!(Edges::Bounded.offset((0, 0), (1, 1), 2, size), Some((2, 2)));
+        assert_eq!(Edges::Bounded.offset((0, 0), (1, 1), 3, size), None);
+        assert_eq!(Edges::Wrap.offset((0, 0), (1, 1), 3, size), Some((3, 0)));
+        assert_eq!(Edges::Wrap.offset((1, 1), (0, 1), -7, size), Some((1, 0)));
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let size = (3, 3);
+        let corner = Edges::Bounded.neighbours((0, 0), &NEIGHBOURS, size);
+        assert_eq!(corner.collect::<Vec<_>>(), vec![(1, 0), (1, 1), (0, 1)]);
+        assert_eq!(Edges::Wrap.neighbours((0, 0), &NEIGHBOURS, size).count(), 8);
+
+        let tiny = Edges::Wrap.neighbours((0, 0), &ORTHOGONAL_NEIGHBOURS, (1, 2));
+        assert_eq!(
+            tiny.collect::<Vec<_>>(),
+            vec![(0, 1), (0, 0), (0, 1), (0, 0)]
+        );
+    }
+}
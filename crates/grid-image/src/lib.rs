@@ -0,0 +1,334 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+    pub const RED: Rgb = Rgb(220, 40, 40);
+    pub const GREEN: Rgb = Rgb(40, 200, 60);
+    pub const BLUE: Rgb = Rgb(50, 90, 220);
+    pub const YELLOW: Rgb = Rgb(230, 200, 40);
+
+    /// A stable, reasonably bright colour for any key, e.g. an antenna
+    /// frequency, so that different keys can be told apart.
+    pub fn from_key(key: u32) -> Rgb {
+        let hash = key.wrapping_mul(0x9e37_79b9);
+        Rgb(
+            96 + (hash >> 24) as u8 % 160,
+            96 + (hash >> 16) as u8 % 160,
+            96 + (hash >> 8) as u8 % 160,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ppm,
+    Png,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+        }
+    }
+
+    /// Picks the format from a path's extension, defaulting to PNG.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ppm") => Format::Ppm,
+            _ => Format::Png,
+        }
+    }
+}
+
+/// A grid of cells drawn as square blocks of pixels.  A base layer is filled
+/// from a per-cell colour mapping and overlays (found words, paths, ...) are
+/// painted on top of it.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    grid_width: usize,
+    grid_height: usize,
+    cell_size: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    pub fn new(grid_width: usize, grid_height: usize, cell_size: usize) -> Self {
+        assert!(cell_size > 0, "Cells need to be at least one pixel");
+
+        Canvas {
+            grid_width,
+            grid_height,
+            cell_size,
+            pixels: vec![Rgb::BLACK; grid_width * grid_height * cell_size * cell_size],
+        }
+    }
+
+    pub fn image_width(&self) -> usize {
+        self.grid_width * self.cell_size
+    }
+
+    pub fn image_height(&self) -> usize {
+        self.grid_height * self.cell_size
+    }
+
+    /// Colours every cell using the given mapping.
+    pub fn fill(&mut self, colour: impl Fn(usize, usize) -> Rgb) {
+        for y in 0..self.grid_height {
+            for x in 0..self.grid_width {
+                self.paint(x, y, 0, colour(x, y));
+            }
+        }
+    }
+
+    /// Paints over whole cells.
+    pub fn overlay(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, colour: Rgb) {
+        for (x, y) in cells {
+            self.paint(x, y, 0, colour);
+        }
+    }
+
+    /// Paints a smaller square in the middle of each cell, so that whatever is
+    /// underneath still shows around it.
+    pub fn mark(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, colour: Rgb) {
+        let inset = self.cell_size / 4;
+        for (x, y) in cells {
+            self.paint(x, y, inset, colour);
+        }
+    }
+
+    fn paint(&mut self, x: usize, y: usize, inset: usize, colour: Rgb) {
+        if x >= self.grid_width || y >= self.grid_height {
+            return;
+        }
+
+        let image_width = self.image_width();
+        for py in y * self.cell_size + inset..(y + 1) * self.cell_size - inset {
+            let row = py * image_width;
+            for px in x * self.cell_size + inset..(x + 1) * self.cell_size - inset {
+                self.pixels[row + px] = colour;
+            }
+        }
+    }
+
+    /// Writes a binary PPM (P6) image.
+    pub fn write_ppm(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "P6\n{} {}\n255\n",
+            self.image_width(),
+            self.image_height()
+        )?;
+        for &Rgb(r, g, b) in &self.pixels {
+            writer.write_all(&[r, g, b])?;
+        }
+        Ok(())
+    }
+
+    /// Writes an 8-bit RGB PNG image.  The pixel data is stored without
+    /// compression, which keeps the encoder tiny; tools that stitch frames into
+    /// a video recompress anyway.
+    pub fn write_png(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (width, height) = (self.image_width(), self.image_height());
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        // Bit depth 8, colour type RGB, default compression, filter and no interlacing.
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_png_chunk(writer, b"IHDR", &header)?;
+
+        // Each row is prefixed by its filter type, which is always "none".
+        let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+        for row in self.pixels.chunks(width.max(1)) {
+            raw.push(0);
+            for &Rgb(r, g, b) in row {
+                raw.extend_from_slice(&[r, g, b]);
+            }
+        }
+        write_png_chunk(writer, b"IDAT", &zlib_stored(&raw))?;
+
+        write_png_chunk(writer, b"IEND", &[])
+    }
+
+    /// Writes the image to a file, choosing the format from the extension.
+    /// The file's directory is created if it doesn't exist yet.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        match Format::from_path(path) {
+            Format::Ppm => self.write_ppm(&mut writer)?,
+            Format::Png => self.write_png(&mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+/// Writes a numbered sequence of images into a directory, e.g.
+/// `frames/frame_00000.png`, ready to be stitched into a video with something
+/// like `ffmpeg -i frames/frame_%05d.png out.mp4`.
+pub struct FrameWriter {
+    directory: PathBuf,
+    format: Format,
+    next: usize,
+}
+
+impl FrameWriter {
+    pub fn new(directory: impl Into<PathBuf>, format: Format) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(FrameWriter {
+            directory,
+            format,
+            next: 0,
+        })
+    }
+
+    /// Writes the next frame and returns its path.
+    pub fn write(&mut self, canvas: &Canvas) -> io::Result<PathBuf> {
+        let path = self.directory.join(format!(
+            "frame_{:05}.{}",
+            self.next,
+            self.format.extension()
+        ));
+        canvas.save(&path)?;
+        self.next += 1;
+        Ok(path)
+    }
+
+    pub fn frames_written(&self) -> usize {
+        self.next
+    }
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let crc = crc32(&[kind, data]);
+    writer.write_all(&crc.to_be_bytes())
+}
+
+// Wraps the data in a zlib stream made of uncompressed ("stored") deflate
+// blocks, which can hold at most 65535 bytes each.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(&[b"IEND"]), 0xae42_6082);
+        assert_eq!(crc32(&[b"123456789"]), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_ppm_layers() {
+        let mut canvas = Canvas::new(2, 1, 4);
+        canvas.fill(|x, _| if x == 0 { Rgb::RED } else { Rgb::BLUE });
+        canvas.mark([(1, 0)], Rgb::WHITE);
+
+        let mut ppm = Vec::new();
+        canvas.write_ppm(&mut ppm).unwrap();
+
+        let header = b"P6\n8 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 8 * 4 * 3);
+
+        // The marked cell keeps its border but has a white middle.
+        assert_eq!(canvas.pixels[4], Rgb::BLUE);
+        assert_eq!(canvas.pixels[8 + 5], Rgb::WHITE);
+        assert_eq!(canvas.pixels[8 + 1], Rgb::RED);
+    }
+
+    #[test]
+    fn test_png_structure() {
+        let mut canvas = Canvas::new(3, 2, 1);
+        canvas.fill(|_, _| Rgb::GREEN);
+
+        let mut png = Vec::new();
+        canvas.write_png(&mut png).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(
+            &png[png.len() - 8..],
+            &[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn test_save_creates_directory() {
+        let directory = std::env::temp_dir().join(format!("grid-image-{}", std::process::id()));
+        let path = directory.join("images").join("grid.ppm");
+
+        Canvas::new(1, 1, 1).save(&path).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap().len(),
+            b"P6\n1 1\n255\n".len() + 3
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// The number of threads to use when none is given.
+pub fn available_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Reads the thread count from a `--jobs N` or `--jobs=N` command line
+/// argument, falling back to `available_jobs()`.
+pub fn jobs_from_args() -> usize {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = if arg == "--jobs" {
+            args.next()
+        } else {
+            arg.strip_prefix("--jobs=").map(str::to_string)
+        };
+
+        if let Some(value) = value {
+            return parse_jobs(&value);
+        }
+    }
+
+    available_jobs()
+}
+
+/// Parses a thread count, treating 0 as "as many as there are cores".
+pub fn parse_jobs(value: &str) -> usize {
+    match value.parse() {
+        Ok(0) => available_jobs(),
+        Ok(jobs) => jobs,
+        Err(_) => panic!("Invalid number of jobs: {}", value),
+    }
+}
+
+/// Applies `f` to every item using up to `jobs` scoped threads.  Each thread
+/// takes the next unclaimed item whenever it finishes one, so a run of slow
+/// items doesn't all land on the same thread.  The results are returned in
+/// the same order as the items no matter how the threads are scheduled.
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.max(1).min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let (next, f) = (&next, &f);
+
+    let mut results = thread::scope(|scope| {
+        let handles = (0..jobs)
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return results;
+                        };
+                        results.push((index, f(item)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Worker thread panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_unstable_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Like `parallel_map`, but takes any iterator of work items.  The items are
+/// collected first so that they can be shared out between the threads.
+pub fn parallel_map_iter<I, R, F>(items: I, jobs: usize, f: F) -> Vec<R>
+where
+    I: IntoIterator,
+    I::Item: Sync,
+    R: Send,
+    F: Fn(&I::Item) -> R + Sync,
+{
+    let items = items.into_iter().collect::<Vec<_>>();
+    parallel_map(&items, jobs, f)
+}
+
+/// Maps the items in parallel and folds the results in item order, so the
+/// reduction doesn't need to be commutative to be deterministic.
+pub fn parallel_map_reduce<T, R, A, F, G>(items: &[T], jobs: usize, f: F, init: A, reduce: G) -> A
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    G: FnMut(A, R) -> A,
+{
+    parallel_map(items, jobs, f).into_iter().fold(init, reduce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items = (0..1000).collect::<Vec<u64>>();
+        let expected = items.iter().map(|x| x * x).collect::<Vec<_>>();
+
+        for jobs in [0, 1, 3, 8, 2000] {
+            assert_eq!(parallel_map(&items, jobs, |x| x * x), expected);
+        }
+        assert_eq!(parallel_map(&[] as &[u64], 4, |x| x * x), vec![]);
+    }
+
+    #[test]
+    fn test_parallel_map_shares_out_items() {
+        use std::sync::Barrier;
+
+        // The first 4 items only finish once all 4 have started, so this only
+        // completes if they're run by different threads at the same time.
+        // Splitting into fixed chunks would give all of them to one thread.
+        let items = (0..16).collect::<Vec<u64>>();
+        let barrier = Barrier::new(4);
+        let doubled = parallel_map(&items, 4, |&x| {
+            if x < 4 {
+                barrier.wait();
+            }
+            x * 2
+        });
+
+        assert_eq!(doubled, items.iter().map(|x| x * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parallel_map_reduce_is_ordered() {
+        let items = ["a", "b", "c", "d", "e"];
+        let joined = parallel_map_reduce(
+            &items,
+            2,
+            |s| s.to_uppercase(),
+            String::new(),
+            |acc, s| acc + &s,
+        );
+
+        assert_eq!(joined, "ABCDE");
+        assert_eq!(parallel_map_iter(1..=4, 3, |x| x + 1), vec![2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,328 @@
+/// A cursor over some text, for picking tokens out of mostly unstructured
+/// input.  Each method either consumes what it was asked for and moves the
+/// cursor past it, or fails and leaves the cursor where it was.
+#[derive(Debug, Clone)]
+pub struct Scanner<'a> {
+    text: &'a str,
+    cursor: usize,
+}
+
+/// A saved cursor position to rewind to, e.g. when a longer token turns out
+/// not to match after all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// A set of literals to search for together, using an Aho-Corasick automaton
+/// so that the text is read once, a byte at a time, however many literals
+/// there are.
+///
+/// The literal found is the one that ends first, and of those ending at the
+/// same place the longest.  This is the rule day03 always used: it searched
+/// for each literal in turn and took the nearest end.  It means a literal
+/// found inside a longer one wins, e.g. `bc` rather than `abcd` in "abcd".
+#[derive(Debug, Clone)]
+pub struct Keywords<'k> {
+    literals: Vec<&'k str>,
+
+    // The state to move to from each state on reading each byte.  State 0 is
+    // the start, where nothing of any literal has been read.
+    next: Vec<[usize; 256]>,
+
+    // The longest literal that ends on reaching each state, if any.
+    found: Vec<Option<usize>>,
+}
+
+/// A range of byte offsets into the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text, cursor: 0 }
+    }
+
+    /// The byte offset of the cursor.
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    /// The text from the cursor onwards.
+    pub fn rest(&self) -> &'a str {
+        &self.text[self.cursor..]
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.cursor == self.text.len()
+    }
+
+    /// The character at the cursor, without consuming it.
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Consumes the literal if the text at the cursor starts with it.
+    pub fn expect_literal(&mut self, literal: &str) -> Option<Span> {
+        if !self.rest().starts_with(literal) {
+            return None;
+        }
+
+        let start = self.cursor;
+        self.cursor += literal.len();
+        Some(Span {
+            start,
+            end: self.cursor,
+        })
+    }
+
+    /// Skips ahead to the first of the literals found from the cursor, and
+    /// consumes it.  Returns which literal it was (its index) and where it
+    /// was.  `Keywords` describes which literal counts as the first.
+    ///
+    /// This builds the `Keywords` automaton on every call, at a table of 256
+    /// entries per state.  To search for the same literals repeatedly, build
+    /// the `Keywords` once and use `find_next`.
+    pub fn find_next_of(&mut self, literals: &[&str]) -> Option<(usize, Span)> {
+        self.find_next(&Keywords::new(literals))
+    }
+
+    /// As `find_next_of`, with the `Keywords` built beforehand.
+    pub fn find_next(&mut self, keywords: &Keywords) -> Option<(usize, Span)> {
+        let (index, span) = keywords.find_at(self.text, self.cursor)?;
+        self.cursor = span.end;
+        Some((index, span))
+    }
+
+    /// Consumes a run of decimal digits, as long as there is at least one and
+    /// their value is no more than `max`.
+    pub fn number_bounded(&mut self, max: u32) -> Option<u32> {
+        let digits = self
+            .rest()
+            .bytes()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return None;
+        }
+
+        let number = self.rest()[..digits].parse::<u32>().ok()?;
+        if number > max {
+            return None;
+        }
+
+        self.cursor += digits;
+        Some(number)
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.cursor)
+    }
+
+    /// Moves the cursor back (or forward) to a checkpoint.
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.cursor = checkpoint.0;
+    }
+
+    /// The span from a checkpoint up to the cursor, e.g. to report where a
+    /// whole token was found.
+    pub fn span_since(&self, checkpoint: Checkpoint) -> Span {
+        Span {
+            start: checkpoint.0,
+            end: self.cursor,
+        }
+    }
+
+    /// The text covered by a span.
+    pub fn slice(&self, span: Span) -> &'a str {
+        &self.text[span.start..span.end]
+    }
+}
+
+impl<'k> Keywords<'k> {
+    /// Empty literals are never found.
+    pub fn new(literals: &[&'k str]) -> Self {
+        // Build a trie of the literals, with a state for each prefix...
+        let mut next = vec![[0; 256]];
+        let mut found = vec![None];
+        for (index, literal) in literals.iter().enumerate() {
+            if literal.is_empty() {
+                continue;
+            }
+
+            let mut state = 0;
+            for &byte in literal.as_bytes() {
+                if next[state][byte as usize] == 0 {
+                    next.push([0; 256]);
+                    found.push(None);
+                    next[state][byte as usize] = next.len() - 1;
+                }
+                state = next[state][byte as usize];
+            }
+            // A repeated literal is found as its first copy.
+            found[state].get_or_insert(index);
+        }
+
+        // ...then fill in the missing moves, breadth first.  Those from a
+        // state are the moves from the state for its longest proper suffix
+        // that is also a prefix, which is always nearer the start.  A state
+        // with no literal of its own ends the literals of that suffix.
+        let mut queue = next[0]
+            .iter()
+            .copied()
+            .filter(|&state| state != 0)
+            .collect::<std::collections::VecDeque<_>>();
+        let mut suffix = vec![0; next.len()];
+        while let Some(state) = queue.pop_front() {
+            if found[state].is_none() {
+                found[state] = found[suffix[state]];
+            }
+            let fallback = next[suffix[state]];
+            for (child, fallback) in next[state].iter_mut().zip(fallback) {
+                if *child == 0 {
+                    *child = fallback;
+                } else {
+                    suffix[*child] = fallback;
+                    queue.push_back(*child);
+                }
+            }
+        }
+
+        Self {
+            literals: literals.to_vec(),
+            next,
+            found,
+        }
+    }
+
+    /// The first literal found in the text at or after byte offset `from`, as
+    /// its index and where it is.
+    pub fn find_at(&self, text: &str, from: usize) -> Option<(usize, Span)> {
+        let mut state = 0;
+        for (end, &byte) in text.as_bytes().iter().enumerate().skip(from) {
+            state = self.next[state][byte as usize];
+            if let Some(index) = self.found[state] {
+                let end = end + 1;
+                let start = end - self.literals[index].len();
+                return Some((index, Span { start, end }));
+            }
+        }
+        None
+    }
+
+    /// Every literal in the text, in order and without overlaps.
+    pub fn find_all<'t>(&'t self, text: &'t str) -> impl Iterator<Item = (usize, Span)> + 't {
+        let mut from = 0;
+        std::iter::from_fn(move || {
+            let (index, span) = self.find_at(text, from)?;
+            from = span.end;
+            Some((index, span))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literals_and_numbers() {
+        let mut scanner = Scanner::new("mul(44,1000)");
+        assert_eq!(scanner.peek(), Some('m'));
+        assert_eq!(scanner.expect_literal("mux("), None);
+        assert_eq!(
+            scanner.expect_literal("mul("),
+            Some(Span { start: 0, end: 4 })
+        );
+        assert_eq!(scanner.number_bounded(9), None);
+        assert_eq!(scanner.position(), 4);
+        assert_eq!(scanner.number_bounded(999), Some(44));
+        assert_eq!(scanner.expect_literal(","), Some(Span { start: 6, end: 7 }));
+        assert_eq!(scanner.number_bounded(999), None);
+        assert_eq!(scanner.number_bounded(u32::MAX), Some(1000));
+        assert_eq!(
+            scanner.expect_literal(")"),
+            Some(Span { start: 11, end: 12 })
+        );
+        assert!(scanner.is_at_end());
+        assert_eq!(scanner.peek(), None);
+
+        let mut scanner = Scanner::new("99999999999");
+        assert_eq!(scanner.number_bounded(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_find_next_of() {
+        let mut scanner = Scanner::new("xdon't()do()ab");
+        let keywords = ["mul(", "don't()", "do()"];
+
+        assert_eq!(
+            scanner.find_next_of(&keywords),
+            Some((1, Span { start: 1, end: 8 }))
+        );
+        assert_eq!(
+            scanner.find_next_of(&keywords),
+            Some((2, Span { start: 8, end: 12 }))
+        );
+        assert_eq!(scanner.find_next_of(&keywords), None);
+        assert_eq!(scanner.rest(), "ab");
+    }
+
+    #[test]
+    fn test_find_next() {
+        // As day03 always did, the keyword that ends first wins, even if a
+        // longer one starts before it.
+        let mut scanner = Scanner::new("abcd");
+        let keywords = Keywords::new(&["abcd", "bc"]);
+        assert_eq!(
+            scanner.find_next(&keywords),
+            Some((1, Span { start: 1, end: 3 }))
+        );
+        assert_eq!(scanner.rest(), "d");
+    }
+
+    #[test]
+    fn test_keywords() {
+        let keywords = Keywords::new(&["ab", "abc", "b", "", "bc"]);
+        let hits = keywords
+            .find_all("xabcbcab")
+            .map(|(index, span)| (index, span.start))
+            .collect::<Vec<_>>();
+        assert_eq!(hits, vec![(0, 1), (2, 4), (0, 6)]);
+
+        assert_eq!(
+            keywords.find_at("xabcbcab", 7),
+            Some((2, Span { start: 7, end: 8 }))
+        );
+        assert_eq!(keywords.find_at("xabcbcab", 8), None);
+        assert_eq!(Keywords::new(&[]).find_at("abc", 0), None);
+
+        // The first literal to end wins, even inside a longer one, and of
+        // those ending together the longest.
+        let keywords = Keywords::new(&["abcd", "bc", "c", "bc"]);
+        assert_eq!(
+            keywords.find_at("abcd", 0),
+            Some((1, Span { start: 1, end: 3 }))
+        );
+        assert_eq!(
+            keywords.find_at("xxabcx", 0),
+            Some((1, Span { start: 3, end: 5 }))
+        );
+        assert_eq!(keywords.find_at("abcd", 3), None);
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut scanner = Scanner::new("abc123def");
+        scanner.expect_literal("abc");
+        let checkpoint = scanner.checkpoint();
+        assert_eq!(scanner.number_bounded(999), Some(123));
+        let span = scanner.span_since(checkpoint);
+        assert_eq!(span, Span { start: 3, end: 6 });
+        assert_eq!(scanner.slice(span), "123");
+
+        scanner.rewind(checkpoint);
+        assert_eq!(scanner.rest(), "123def");
+    }
+}
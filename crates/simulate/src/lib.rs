@@ -0,0 +1,186 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// What a step function decides to do with the current state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<S> {
+    /// Move on to the given state.
+    Continue(S),
+
+    /// The machine has stopped (e.g. the guard has walked off the map).
+    Halt,
+}
+
+/// How a simulation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The step function halted after `length` states (including the initial one).
+    Halted { length: usize },
+
+    /// A state was revisited.  The states at indices `start..start + period`
+    /// repeat forever.
+    Loop { start: usize, period: usize },
+}
+
+impl Outcome {
+    pub fn is_loop(&self) -> bool {
+        matches!(self, Outcome::Loop { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation<S> {
+    /// Every state visited, in order.  For a loop this stops just before the
+    /// first repeated state.
+    pub trace: Vec<S>,
+    pub outcome: Outcome,
+}
+
+/// Runs the state machine until it halts or revisits a state, remembering
+/// every state it has seen.
+pub fn simulate<S, F>(initial: S, mut step: F) -> Simulation<S>
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> Step<S>,
+{
+    let mut seen = HashMap::new();
+    let mut trace = Vec::new();
+    let mut state = initial;
+
+    loop {
+        if let Some(&start) = seen.get(&state) {
+            let period = trace.len() - start;
+            return Simulation {
+                trace,
+                outcome: Outcome::Loop { start, period },
+            };
+        }
+
+        seen.insert(state.clone(), trace.len());
+        trace.push(state.clone());
+
+        match step(&state) {
+            Step::Continue(next) => state = next,
+            Step::Halt => {
+                let length = trace.len();
+                return Simulation {
+                    trace,
+                    outcome: Outcome::Halted { length },
+                };
+            }
+        }
+    }
+}
+
+/// Runs the state machine until it halts or loops using Brent's algorithm, so
+/// only a couple of states are held at any time.  The step function is called
+/// more often than with [`simulate`], so it should be cheap and deterministic.
+pub fn simulate_brent<S, F>(initial: S, mut step: F) -> Outcome
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> Step<S>,
+{
+    // Find the period: the hare moves one state at a time while the tortoise
+    // teleports to it at every power of two.  The hare visits each state in
+    // order, so it's also the one to notice a halt.
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = initial.clone();
+    let mut hare_index = 1;
+    let mut hare = match step(&initial) {
+        Step::Continue(next) => next,
+        Step::Halt => return Outcome::Halted { length: 1 },
+    };
+
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+
+        hare = match step(&hare) {
+            Step::Continue(next) => next,
+            Step::Halt => return Outcome::Halted { length: hare_index + 1 },
+        };
+        hare_index += 1;
+        period += 1;
+    }
+
+    // Find the start: walk two states `period` apart from the beginning until
+    // they meet.  Both are now known to be on a path that never halts.
+    let advance = |state: &S, step: &mut F| match step(state) {
+        Step::Continue(next) => next,
+        Step::Halt => unreachable!("the state machine is not deterministic"),
+    };
+
+    let mut tortoise = initial.clone();
+    let mut hare = initial;
+    for _ in 0..period {
+        hare = advance(&hare, &mut step);
+    }
+
+    let mut start = 0;
+    while tortoise != hare {
+        tortoise = advance(&tortoise, &mut step);
+        hare = advance(&hare, &mut step);
+        start += 1;
+    }
+
+    Outcome::Loop { start, period }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // x -> x + 1 until 10, then back to 4: a tail of 4 and a loop of 7.
+    fn looping(x: &u32) -> Step<u32> {
+        Step::Continue(if *x == 10 { 4 } else { x + 1 })
+    }
+
+    fn halting(x: &u32) -> Step<u32> {
+        if *x == 5 {
+            Step::Halt
+        } else {
+            Step::Continue(x + 1)
+        }
+    }
+
+    #[test]
+    fn test_simulate_loop() {
+        let simulation = simulate(0, looping);
+
+        assert_eq!(simulation.trace, (0..=10).collect::<Vec<_>>());
+        assert_eq!(simulation.outcome, Outcome::Loop { start: 4, period: 7 });
+    }
+
+    #[test]
+    fn test_simulate_halt() {
+        let simulation = simulate(0, halting);
+
+        assert_eq!(simulation.trace, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(simulation.outcome, Outcome::Halted { length: 6 });
+    }
+
+    #[test]
+    fn test_brent_matches_simulate() {
+        for initial in 0..=10 {
+            assert_eq!(
+                simulate_brent(initial, looping),
+                simulate(initial, looping).outcome
+            );
+        }
+        for initial in 0..=5 {
+            assert_eq!(
+                simulate_brent(initial, halting),
+                simulate(initial, halting).outcome
+            );
+        }
+
+        // A fixed point is a loop of period 1.
+        assert_eq!(
+            simulate_brent(3, |&x| Step::Continue(x)),
+            Outcome::Loop { start: 0, period: 1 }
+        );
+    }
+}